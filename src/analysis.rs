@@ -0,0 +1,240 @@
+use std::fmt;
+
+use crate::error::CalcError;
+use crate::expr::{self, Statement, UserFunction};
+
+/// Highest polynomial degree a fit may ask for. Beyond this the normal
+/// equations are too ill-conditioned to be worth solving.
+pub const MAX_DEGREE: usize = 10;
+
+/// Name frontends give the function a fit defines unless told otherwise.
+pub const DEFAULT_FUNCTION: &str = "predict";
+
+/// The curve a least-squares fit looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// `y = a + b·x`
+    Linear,
+    /// `y = c0 + c1·x + … + cn·xⁿ` of the given degree.
+    Polynomial(usize),
+    /// `y = a·e^(b·x)`, fitted on `ln y`; needs positive `y`.
+    Exponential,
+    /// `y = a + b·ln x`; needs positive `x`.
+    Logarithmic,
+    /// `y = a·x^b`, fitted on `ln x` and `ln y`; needs both positive.
+    Power,
+}
+
+impl Model {
+    pub const NAMES: [&'static str; 5] = ["linear", "polynomial", "exponential", "logarithmic", "power"];
+
+    /// Looks a model up by name. Only `polynomial` uses `degree`, and requires it.
+    pub fn new(name: &str, degree: Option<usize>) -> Result<Self, CalcError> {
+        let model = match name.trim().to_lowercase().as_str() {
+            "linear" => Model::Linear,
+            "polynomial" => {
+                let degree = degree.ok_or(CalcError::MissingOperand("degree"))?;
+                if !(1..=MAX_DEGREE).contains(&degree) {
+                    return Err(CalcError::InvalidRequest(format!(
+                        "Polynomial degree must be between 1 and {}, not {}",
+                        MAX_DEGREE, degree
+                    )));
+                }
+                Model::Polynomial(degree)
+            }
+            "exponential" => Model::Exponential,
+            "logarithmic" => Model::Logarithmic,
+            "power" => Model::Power,
+            _ => {
+                return Err(CalcError::InvalidRequest(format!(
+                    "Unknown model '{}'; use {}",
+                    name,
+                    Model::NAMES.join(", ")
+                )))
+            }
+        };
+        Ok(model)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Model::Linear => "linear",
+            Model::Polynomial(_) => "polynomial",
+            Model::Exponential => "exponential",
+            Model::Logarithmic => "logarithmic",
+            Model::Power => "power",
+        }
+    }
+
+    /// Number of coefficients, and so the fewest points that determine them.
+    fn unknowns(self) -> usize {
+        match self {
+            Model::Polynomial(degree) => degree + 1,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Model::Polynomial(degree) => write!(f, "polynomial (degree {})", degree),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// A fitted curve and how well it matches the data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fit {
+    pub model: Model,
+    /// `[a, b]` as in the model's formula, or `[c0, …, cn]` for polynomials.
+    pub coefficients: Vec<f64>,
+    /// Coefficient of determination of the curve against the original `y`.
+    pub r_squared: f64,
+    /// `y - predict(x)` for every point, in input order.
+    pub residuals: Vec<f64>,
+}
+
+impl Fit {
+    pub fn predict(&self, x: f64) -> f64 {
+        let c = &self.coefficients;
+        match self.model {
+            Model::Linear | Model::Polynomial(_) => c.iter().rev().fold(0.0, |sum, c| sum * x + c),
+            Model::Exponential => c[0] * (c[1] * x).exp(),
+            Model::Logarithmic => c[0] + c[1] * x.ln(),
+            Model::Power => c[0] * x.powf(c[1]),
+        }
+    }
+
+    /// The curve as an expression in `x`, e.g. `1.5 + 2*x - 0.25*x^2`.
+    pub fn equation(&self) -> String {
+        let c = &self.coefficients;
+        match self.model {
+            Model::Linear | Model::Polynomial(_) => {
+                let mut equation = c[0].to_string();
+                for (power, &coefficient) in c.iter().enumerate().skip(1) {
+                    let variable = if power == 1 { "x".to_string() } else { format!("x^{}", power) };
+                    let sign = if coefficient < 0.0 { '-' } else { '+' };
+                    equation.push_str(&format!(" {} {}*{}", sign, coefficient.abs(), variable));
+                }
+                equation
+            }
+            Model::Exponential => format!("{}*e^({}*x)", c[0], c[1]),
+            Model::Logarithmic => {
+                let sign = if c[1] < 0.0 { '-' } else { '+' };
+                format!("{} {} {}*ln(x)", c[0], sign, c[1].abs())
+            }
+            Model::Power if c[1] < 0.0 => format!("{}*x^({})", c[0], c[1]),
+            Model::Power => format!("{}*x^{}", c[0], c[1]),
+        }
+    }
+
+    /// The curve as a user-defined function `name(x)`, so expressions can
+    /// call it to predict new values.
+    pub fn function(&self, name: &str) -> Result<UserFunction, CalcError> {
+        match expr::parse_statement(&format!("{}(x) = {}", name, self.equation()))? {
+            Statement::Definition { function, .. } => Ok(function),
+            _ => Err(CalcError::InvalidRequest(format!("'{}' is not a valid function name", name))),
+        }
+    }
+}
+
+/// Fits `model` to the points `(x[i], y[i])` by least squares.
+/// Exponential and power curves are fitted on logarithms, which weighs
+/// relative rather than absolute errors; R² and the residuals are still
+/// measured against `y` itself.
+pub fn fit(model: Model, x: &[f64], y: &[f64]) -> Result<Fit, CalcError> {
+    if x.len() != y.len() {
+        return Err(CalcError::InvalidRequest(format!(
+            "x has {} values but y has {}",
+            x.len(),
+            y.len()
+        )));
+    }
+    if x.iter().any(|x| !x.is_finite()) {
+        return Err(CalcError::InvalidOperand("x"));
+    }
+    if y.iter().any(|y| !y.is_finite()) {
+        return Err(CalcError::InvalidOperand("y"));
+    }
+    if x.len() < model.unknowns() {
+        return Err(CalcError::Domain(format!(
+            "A {} fit needs at least {} points",
+            model,
+            model.unknowns()
+        )));
+    }
+    let positive = |values: &[f64], axis: &str| {
+        if values.iter().all(|&value| value > 0.0) {
+            Ok(values.iter().map(|value| value.ln()).collect::<Vec<_>>())
+        } else {
+            Err(CalcError::Domain(format!("A {} fit needs every {} to be positive", model, axis)))
+        }
+    };
+
+    let coefficients = match model {
+        Model::Linear => polynomial(x, y, 1),
+        Model::Polynomial(degree) => polynomial(x, y, degree),
+        Model::Exponential => polynomial(x, &positive(y, "y")?, 1).map(|c| vec![c[0].exp(), c[1]]),
+        Model::Logarithmic => polynomial(&positive(x, "x")?, y, 1),
+        Model::Power => polynomial(&positive(x, "x")?, &positive(y, "y")?, 1).map(|c| vec![c[0].exp(), c[1]]),
+    }
+    .filter(|c| c.iter().all(|c| c.is_finite()))
+    // Adding zero turns any -0 into 0, which reads better in equations
+    .map(|c| c.into_iter().map(|c| c + 0.0).collect())
+    .ok_or_else(|| CalcError::Domain(format!("The x values do not determine a {} fit", model)))?;
+
+    let mut fit = Fit { model, coefficients, r_squared: 0.0, residuals: Vec::new() };
+    fit.residuals = x.iter().zip(y).map(|(&x, &y)| y - fit.predict(x)).collect();
+    let mean = y.iter().sum::<f64>() / y.len() as f64;
+    let total: f64 = y.iter().map(|y| (y - mean).powi(2)).sum();
+    let unexplained: f64 = fit.residuals.iter().map(|r| r * r).sum();
+    fit.r_squared = if total == 0.0 { 1.0 } else { 1.0 - unexplained / total };
+    Ok(fit)
+}
+
+/// Least-squares polynomial coefficients, lowest power first, by solving
+/// the normal equations. `None` if they are singular.
+fn polynomial(x: &[f64], y: &[f64], degree: usize) -> Option<Vec<f64>> {
+    let size = degree + 1;
+    // Augmented matrix of the normal equations: Σ x^(i+j) · c_j = Σ x^i · y
+    let mut matrix = vec![vec![0.0; size + 1]; size];
+    for (&x, &y) in x.iter().zip(y) {
+        let powers: Vec<f64> = (0..=2 * degree).map(|power| x.powi(power as i32)).collect();
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().take(size).enumerate() {
+                *cell += powers[i + j];
+            }
+            row[size] += powers[i] * y;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting
+    let scale = matrix
+        .iter()
+        .flat_map(|row| &row[..size])
+        .fold(0.0, |max: f64, cell| max.max(cell.abs()));
+    for column in 0..size {
+        let pivot = (column..size).max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() <= scale * 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        let (pivot_rows, rows) = matrix.split_at_mut(column + 1);
+        let pivot_row = &pivot_rows[column];
+        for row in rows {
+            let factor = row[column] / pivot_row[column];
+            for (cell, pivot_cell) in row.iter_mut().zip(pivot_row).skip(column) {
+                *cell -= factor * pivot_cell;
+            }
+        }
+    }
+
+    let mut coefficients = vec![0.0; size];
+    for row in (0..size).rev() {
+        let known: f64 = (row + 1..size).map(|k| matrix[row][k] * coefficients[k]).sum();
+        coefficients[row] = (matrix[row][size] - known) / matrix[row][row];
+    }
+    Some(coefficients)
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Radian inputs within this many quarter turns of a multiple of π/2 are
+/// treated as landing exactly on it.
+const QUARTER_TURN_TOLERANCE: f64 = 1e-12;
+
+/// Unit in which trigonometric inputs and inverse-trigonometric outputs are expressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AngleUnit {
+    #[default]
+    #[serde(alias = "deg")]
+    Degrees,
+    #[serde(alias = "rad")]
+    Radians,
+    #[serde(alias = "grad")]
+    Gradians,
+}
+
+impl AngleUnit {
+    /// How many of this unit make up half a turn.
+    fn half_turn(self) -> f64 {
+        match self {
+            AngleUnit::Degrees => 180.0,
+            AngleUnit::Radians => PI,
+            AngleUnit::Gradians => 200.0,
+        }
+    }
+
+    pub fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Radians => angle,
+            unit => angle * PI / unit.half_turn(),
+        }
+    }
+
+    pub fn radians_to_unit(self, radians: f64) -> f64 {
+        match self {
+            AngleUnit::Radians => radians,
+            unit => radians * unit.half_turn() / PI,
+        }
+    }
+
+    /// Radians with whole turns removed in this unit first, so that large
+    /// degree inputs such as 3600090° lose no precision before conversion.
+    pub fn reduced_radians(self, angle: f64) -> f64 {
+        match self {
+            AngleUnit::Radians => angle,
+            unit => unit.to_radians(angle.rem_euclid(2.0 * unit.half_turn())),
+        }
+    }
+
+    /// Which quarter turn (0 to 3) the angle lies on, if it lies on one.
+    /// Degrees and gradians are checked exactly; radians, which can never
+    /// hit π/2 exactly, within a small tolerance, and only while the quotient
+    /// is fine-grained enough for that tolerance to mean anything.
+    /// Otherwise a huge input such as 1e17 would always look whole.
+    pub fn quarter_turn(self, angle: f64) -> Option<u8> {
+        if !angle.is_finite() {
+            return None;
+        }
+        let quarters = match self {
+            AngleUnit::Radians => {
+                let quarters = angle / FRAC_PI_2;
+                if quarters.abs() * f64::EPSILON >= QUARTER_TURN_TOLERANCE {
+                    return None;
+                }
+                let nearest = quarters.round();
+                if (quarters - nearest).abs() > QUARTER_TURN_TOLERANCE {
+                    return None;
+                }
+                nearest
+            }
+            unit => {
+                let quarter = unit.half_turn() / 2.0;
+                let reduced = angle.rem_euclid(4.0 * quarter);
+                if reduced % quarter != 0.0 {
+                    return None;
+                }
+                reduced / quarter
+            }
+        };
+        Some(quarters.rem_euclid(4.0) as u8)
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            AngleUnit::Degrees => "°",
+            AngleUnit::Radians => " rad",
+            AngleUnit::Gradians => " grad",
+        }
+    }
+
+    /// Formats an angle with its unit, e.g. `30°` or `0.5 rad`.
+    pub fn label(self, angle: impl fmt::Display) -> String {
+        format!("{}{}", angle, self.symbol())
+    }
+}
+
+impl fmt::Display for AngleUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AngleUnit::Degrees => "degrees",
+            AngleUnit::Radians => "radians",
+            AngleUnit::Gradians => "gradians",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for AngleUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "degrees" | "deg" => Ok(AngleUnit::Degrees),
+            "radians" | "rad" => Ok(AngleUnit::Radians),
+            "gradians" | "grad" => Ok(AngleUnit::Gradians),
+            other => Err(format!("Unknown angle unit '{}'", other)),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+const BASE: u64 = 1_000_000_000;
+const BASE_DIGITS: usize = 9;
+
+/// Signed arbitrary-precision integer stored as little-endian base-10^9 limbs,
+/// which keeps decimal formatting trivial. Zero has no limbs and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt { negative: false, limbs: Vec::new() }
+    }
+
+    pub fn from_u64(mut n: u64) -> Self {
+        let mut limbs = Vec::new();
+        while n > 0 {
+            limbs.push((n % BASE) as u32);
+            n /= BASE;
+        }
+        BigInt { negative: false, limbs }
+    }
+
+    /// Exact conversion of an integral, finite float.
+    pub fn from_f64(x: f64) -> Option<Self> {
+        if !x.is_finite() || x != x.trunc() {
+            return None;
+        }
+        if x.abs() < u64::MAX as f64 {
+            let magnitude = BigInt::from_u64(x.abs() as u64);
+            return Some(if x < 0.0 { -magnitude } else { magnitude });
+        }
+
+        // |x| >= 2^64, so x = mantissa * 2^exponent with a positive exponent
+        let bits = x.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64 - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let mut magnitude = BigInt::from_u64(mantissa);
+        for _ in 0..exponent {
+            magnitude.mul_small(2);
+        }
+        Some(if x < 0.0 { -magnitude } else { magnitude })
+    }
+
+    /// Parses an optionally signed decimal integer such as `-123456789012345678901`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut limbs = Vec::with_capacity(digits.len() / BASE_DIGITS + 1);
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(BASE_DIGITS);
+            limbs.push(digits[start..end].parse::<u32>().ok()?);
+            end = start;
+        }
+
+        let mut result = BigInt { negative, limbs };
+        result.normalize();
+        Some(result)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Nearest `f64`, or infinity when the value is out of range.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Value as a `u64`, if it is non-negative and fits.
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.negative {
+            return None;
+        }
+        self.limbs
+            .iter()
+            .rev()
+            .try_fold(0u64, |acc, &limb| acc.checked_mul(BASE)?.checked_add(limb as u64))
+    }
+
+    /// Number of decimal digits in the magnitude.
+    pub fn digit_count(&self) -> usize {
+        match self.limbs.last() {
+            None => 1,
+            Some(top) => (self.limbs.len() - 1) * BASE_DIGITS + top.to_string().len(),
+        }
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut result = BigInt::from_u64(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    pub fn mul_small(&mut self, factor: u32) {
+        let mut carry = 0u64;
+        for limb in self.limbs.iter_mut() {
+            let product = *limb as u64 * factor as u64 + carry;
+            *limb = (product % BASE) as u32;
+            carry = product / BASE;
+        }
+        while carry > 0 {
+            self.limbs.push((carry % BASE) as u32);
+            carry /= BASE;
+        }
+        self.normalize();
+    }
+
+    /// Divides in place, truncating towards zero, and returns the remainder
+    /// of the magnitude.
+    pub fn div_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let current = remainder * BASE + *limb as u64;
+            *limb = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+        self.normalize();
+        remainder as u32
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        if self.limbs.is_empty() {
+            self.negative = false;
+        }
+    }
+
+    fn cmp_magnitude(&self, other: &BigInt) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+
+    fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len().max(b.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..a.len().max(b.len()) {
+            let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+            limbs.push((sum % BASE) as u32);
+            carry = sum / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        limbs
+    }
+
+    /// `a - b` for magnitudes where `a >= b`.
+    fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(a.len());
+        let mut borrow = 0i64;
+        for (i, &limb) in a.iter().enumerate() {
+            let mut diff = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = if diff < 0 {
+                diff += BASE as i64;
+                1
+            } else {
+                0
+            };
+            limbs.push(diff as u32);
+        }
+        limbs
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        let mut result = if self.negative == other.negative {
+            BigInt {
+                negative: self.negative,
+                limbs: BigInt::add_magnitude(&self.limbs, &other.limbs),
+            }
+        } else if self.cmp_magnitude(other) != Ordering::Less {
+            BigInt {
+                negative: self.negative,
+                limbs: BigInt::sub_magnitude(&self.limbs, &other.limbs),
+            }
+        } else {
+            BigInt {
+                negative: other.negative,
+                limbs: BigInt::sub_magnitude(&other.limbs, &self.limbs),
+            }
+        };
+        result.normalize();
+        result
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other.clone()
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        if self.is_zero() || other.is_zero() {
+            return BigInt::zero();
+        }
+
+        let mut wide = vec![0u64; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let current = wide[i + j] + a as u64 * b as u64 + carry;
+                wide[i + j] = current % BASE;
+                carry = current / BASE;
+            }
+            let mut k = i + other.limbs.len();
+            while carry > 0 {
+                let current = wide[k] + carry;
+                wide[k] = current % BASE;
+                carry = current / BASE;
+                k += 1;
+            }
+        }
+
+        let mut result = BigInt {
+            negative: self.negative != other.negative,
+            limbs: wide.into_iter().map(|limb| limb as u32).collect(),
+        };
+        result.normalize();
+        result
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(mut self) -> BigInt {
+        if !self.is_zero() {
+            self.negative = !self.negative;
+        }
+        self
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((top, rest)) = self.limbs.split_last() else {
+            return write!(f, "0");
+        };
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", top)?;
+        for limb in rest.iter().rev() {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,853 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::angle::AngleUnit;
+use crate::bigint::BigInt;
+use crate::complex::Complex;
+use crate::error::CalcError;
+use crate::expr::{self, UserFunction};
+use crate::history::{History, HistoryEntry};
+use crate::memory::{Memory, Register};
+use crate::number_theory::NumberOperation;
+use crate::programmer::{Bases, WordSize};
+use crate::registry::Registry;
+use crate::special;
+
+/// A calculator with its own memory registers, variables, user-defined
+/// functions, recent history, angle unit and word size.
+///
+/// Pure operations take `&self` and never record anything; frontends decide
+/// what goes into the history with [`Calculator::add_to_history`]. Errors are
+/// returned as [`CalcError`] rather than as NaN or infinity.
+#[derive(Debug, Clone, Default)]
+pub struct Calculator {
+    memory: Memory,
+    history: History,
+    variables: BTreeMap<String, f64>,
+    functions: BTreeMap<String, UserFunction>,
+    angle_unit: AngleUnit,
+    word_size: WordSize,
+    registry: Arc<Registry>,
+}
+
+impl Calculator {
+    pub fn new() -> Self {
+        Calculator::default()
+    }
+
+    /// A calculator whose named operations come from `registry`, which may
+    /// be shared between calculators.
+    pub fn with_registry(registry: Arc<Registry>) -> Self {
+        Calculator {
+            registry,
+            ..Calculator::default()
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Calls a registered operation by name, checking arity and domain first.
+    pub fn call(&self, name: &str, args: &[f64], unit: AngleUnit) -> Result<f64, CalcError> {
+        let operator = self
+            .registry
+            .get(name)
+            .ok_or_else(|| CalcError::UnknownFunction(name.to_string()))?;
+        Registry::apply(operator.as_ref(), self, args, unit)
+    }
+
+    /// The unit trigonometric operations use when a caller does not pick one.
+    pub fn angle_unit(&self) -> AngleUnit {
+        self.angle_unit
+    }
+
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.angle_unit = unit;
+    }
+
+    /// The integer type programmer operations work in.
+    pub fn word_size(&self) -> WordSize {
+        self.word_size
+    }
+
+    pub fn set_word_size(&mut self, word_size: WordSize) {
+        self.word_size = word_size;
+    }
+
+    /// The most recent entries, oldest first.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// How many entries the history keeps; older ones are dropped.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.set_capacity(capacity);
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// See [`History::track_unsaved`].
+    pub fn track_unsaved_history(&mut self) {
+        self.history.track_unsaved();
+    }
+
+    /// Entries recorded since the last call, for persisting them.
+    pub fn take_unsaved_history(&mut self) -> Vec<HistoryEntry> {
+        self.history.take_unsaved()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    /// Every variable by name, `ans` included once a result exists.
+    pub fn variables(&self) -> &BTreeMap<String, f64> {
+        &self.variables
+    }
+
+    /// Assigns a variable. The constants `pi` and `e` and the automatic
+    /// `ans` cannot be assigned.
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<(), CalcError> {
+        if RESERVED_NAMES.contains(&name) {
+            return Err(CalcError::ReservedName(name.to_string()));
+        }
+        self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove_variable(&mut self, name: &str) -> Option<f64> {
+        self.variables.remove(name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&UserFunction> {
+        self.functions.get(name)
+    }
+
+    /// Every user-defined function by name.
+    pub fn functions(&self) -> &BTreeMap<String, UserFunction> {
+        &self.functions
+    }
+
+    /// Defines or replaces a function. Registered operations cannot be
+    /// shadowed, and a definition that would let a function end up calling
+    /// itself is rejected.
+    pub fn define_function(&mut self, name: &str, function: UserFunction) -> Result<(), CalcError> {
+        if name == expr::LOG10_ALIAS || self.registry.get(name).is_some() {
+            return Err(CalcError::ReservedName(name.to_string()));
+        }
+
+        let mut pending: Vec<&str> = function.body.called_functions();
+        let mut visited: Vec<&str> = Vec::new();
+        while let Some(callee) = pending.pop() {
+            if callee == name {
+                return Err(CalcError::Recursion(format!("'{}' would call itself", name)));
+            }
+            if visited.contains(&callee) {
+                continue;
+            }
+            visited.push(callee);
+            if let Some(existing) = self.functions.get(callee) {
+                pending.extend(existing.body.called_functions());
+            }
+        }
+
+        self.add_to_history(HistoryEntry::success("define", &format!("Defined {}", function.source), None));
+        self.functions.insert(name.to_string(), function);
+        Ok(())
+    }
+
+    pub fn remove_function(&mut self, name: &str) -> Option<UserFunction> {
+        self.functions.remove(name)
+    }
+
+    /// Makes `value` available as `ans`.
+    pub fn set_answer(&mut self, value: f64) {
+        self.variables.insert(ANSWER.to_string(), value);
+    }
+
+    /// Records a successful calculation in the history and as `ans`.
+    pub fn record_result(&mut self, operation: &str, expression: &str, value: f64) {
+        self.add_to_history(HistoryEntry::success(operation, expression, Some(value)));
+        self.set_answer(value);
+    }
+
+    /// Records a programmer-mode result, a bit pattern in `word`, in the
+    /// history and as `ans`, and returns it in every base.
+    pub fn record_word_result(&mut self, operation: &str, expression: &str, word: WordSize, pattern: u64) -> Bases {
+        let bases = word.bases(pattern);
+        let value = word.value(pattern) as f64;
+        let entry = HistoryEntry::success(operation, expression, Some(value));
+        self.add_to_history(entry.with_output(format!("{} ({})", bases.decimal, bases.hex)));
+        self.set_answer(value);
+        bases
+    }
+
+    /// Records a number theory result in the history, written out exactly,
+    /// and its first value as `ans`.
+    pub fn record_integers(&mut self, operation: NumberOperation, expression: &str, results: &[i128]) {
+        let first = results.first().copied().unwrap_or_default() as f64;
+        let entry = HistoryEntry::success(operation.name(), expression, Some(first));
+        self.add_to_history(entry.with_output(operation.describe_results(results)));
+        self.set_answer(first);
+    }
+
+    /// Records a statistic's results in the history and its first result as
+    /// `ans`. Several results, like quartiles, are listed in the output.
+    pub fn record_statistic(&mut self, operation: &str, expression: &str, results: &[f64]) {
+        let Some(&first) = results.first() else {
+            return;
+        };
+        let mut entry = HistoryEntry::success(operation, expression, Some(first));
+        if results.len() > 1 {
+            let results: Vec<String> = results.iter().map(f64::to_string).collect();
+            entry = entry.with_output(format!("[{}]", results.join(", ")));
+        }
+        self.add_to_history(entry);
+        self.set_answer(first);
+    }
+
+    /// Records a failed calculation in the history; `ans` is left alone.
+    pub fn record_failure(&mut self, operation: &str, expression: &str, error: &CalcError) {
+        self.add_to_history(HistoryEntry::failure(operation, expression, error));
+    }
+
+    pub fn add(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        finite(a + b, "Addition overflowed")
+    }
+
+    pub fn subtract(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        finite(a - b, "Subtraction overflowed")
+    }
+
+    pub fn multiply(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        finite(a * b, "Multiplication overflowed")
+    }
+
+    pub fn divide(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        if b == 0.0 {
+            Err(CalcError::DivisionByZero)
+        } else {
+            finite(a / b, "Division overflowed")
+        }
+    }
+
+    pub fn power(&self, base: f64, exponent: f64) -> Result<f64, CalcError> {
+        if base == 0.0 && exponent < 0.0 {
+            return Err(CalcError::DivisionByZero);
+        }
+        let result = base.powf(exponent);
+        if result.is_nan() {
+            Err(CalcError::Domain(
+                "Negative base with a fractional exponent has no real result".to_string(),
+            ))
+        } else {
+            finite(result, "Power overflowed")
+        }
+    }
+
+    pub fn sqrt(&self, x: f64) -> Result<f64, CalcError> {
+        if x < 0.0 {
+            Err(CalcError::Domain("Cannot calculate square root of negative number".to_string()))
+        } else {
+            Ok(x.sqrt())
+        }
+    }
+
+    /// Sine of `angle` in `unit`; exact on quarter turns, so `sin(180°)` is 0.
+    pub fn sin(&self, angle: f64, unit: AngleUnit) -> f64 {
+        match unit.quarter_turn(angle) {
+            Some(0) | Some(2) => 0.0,
+            Some(1) => 1.0,
+            Some(3) => -1.0,
+            _ => unit.reduced_radians(angle).sin(),
+        }
+    }
+
+    pub fn cos(&self, angle: f64, unit: AngleUnit) -> f64 {
+        match unit.quarter_turn(angle) {
+            Some(1) | Some(3) => 0.0,
+            Some(0) => 1.0,
+            Some(2) => -1.0,
+            _ => unit.reduced_radians(angle).cos(),
+        }
+    }
+
+    /// Tangent of `angle` in `unit`; odd quarter turns are a domain error.
+    pub fn tan(&self, angle: f64, unit: AngleUnit) -> Result<f64, CalcError> {
+        match unit.quarter_turn(angle) {
+            Some(1) | Some(3) => Err(CalcError::Domain("Tangent is undefined for this angle".to_string())),
+            Some(_) => Ok(0.0),
+            None => finite(unit.reduced_radians(angle).tan(), "Tangent overflowed"),
+        }
+    }
+
+    pub fn sec(&self, angle: f64, unit: AngleUnit) -> Result<f64, CalcError> {
+        match unit.quarter_turn(angle) {
+            Some(1) | Some(3) => Err(CalcError::Domain("Secant is undefined for this angle".to_string())),
+            _ => Ok(1.0 / self.cos(angle, unit)),
+        }
+    }
+
+    pub fn csc(&self, angle: f64, unit: AngleUnit) -> Result<f64, CalcError> {
+        match unit.quarter_turn(angle) {
+            Some(0) | Some(2) => Err(CalcError::Domain("Cosecant is undefined for this angle".to_string())),
+            _ => Ok(1.0 / self.sin(angle, unit)),
+        }
+    }
+
+    pub fn cot(&self, angle: f64, unit: AngleUnit) -> Result<f64, CalcError> {
+        match unit.quarter_turn(angle) {
+            Some(0) | Some(2) => Err(CalcError::Domain("Cotangent is undefined for this angle".to_string())),
+            Some(_) => Ok(0.0),
+            None => Ok(1.0 / unit.reduced_radians(angle).tan()),
+        }
+    }
+
+    /// Inverse sine in radians; convert with [`AngleUnit::radians_to_unit`].
+    pub fn asin(&self, x: f64) -> Result<f64, CalcError> {
+        if !(-1.0..=1.0).contains(&x) {
+            Err(CalcError::Domain("Inverse sine is only defined for values between -1 and 1".to_string()))
+        } else {
+            Ok(x.asin())
+        }
+    }
+
+    pub fn acos(&self, x: f64) -> Result<f64, CalcError> {
+        if !(-1.0..=1.0).contains(&x) {
+            Err(CalcError::Domain("Inverse cosine is only defined for values between -1 and 1".to_string()))
+        } else {
+            Ok(x.acos())
+        }
+    }
+
+    pub fn atan(&self, x: f64) -> f64 {
+        x.atan()
+    }
+
+    /// Angle of the point `(x, y)` in radians, in (-π, π].
+    pub fn atan2(&self, y: f64, x: f64) -> Result<f64, CalcError> {
+        if y == 0.0 && x == 0.0 {
+            Err(CalcError::Domain("atan2 is undefined when both arguments are zero".to_string()))
+        } else {
+            Ok(y.atan2(x))
+        }
+    }
+
+    pub fn sinh(&self, x: f64) -> Result<f64, CalcError> {
+        finite(x.sinh(), "Hyperbolic sine overflowed")
+    }
+
+    pub fn cosh(&self, x: f64) -> Result<f64, CalcError> {
+        finite(x.cosh(), "Hyperbolic cosine overflowed")
+    }
+
+    pub fn tanh(&self, x: f64) -> f64 {
+        x.tanh()
+    }
+
+    pub fn asinh(&self, x: f64) -> f64 {
+        x.asinh()
+    }
+
+    pub fn acosh(&self, x: f64) -> Result<f64, CalcError> {
+        if x < 1.0 {
+            Err(CalcError::Domain("Inverse hyperbolic cosine is only defined for values of at least 1".to_string()))
+        } else {
+            Ok(x.acosh())
+        }
+    }
+
+    pub fn atanh(&self, x: f64) -> Result<f64, CalcError> {
+        if x <= -1.0 || x >= 1.0 {
+            Err(CalcError::Domain(
+                "Inverse hyperbolic tangent is only defined for values strictly between -1 and 1".to_string(),
+            ))
+        } else {
+            Ok(x.atanh())
+        }
+    }
+
+    pub fn ln(&self, x: f64) -> Result<f64, CalcError> {
+        if x <= 0.0 {
+            Err(CalcError::Domain("Natural logarithm is only defined for positive numbers".to_string()))
+        } else {
+            Ok(x.ln())
+        }
+    }
+
+    pub fn log10(&self, x: f64) -> Result<f64, CalcError> {
+        if x <= 0.0 {
+            Err(CalcError::Domain("Logarithm is only defined for positive numbers".to_string()))
+        } else {
+            Ok(x.log10())
+        }
+    }
+
+    pub fn degrees_to_radians(&self, degrees: f64) -> f64 {
+        degrees * PI / 180.0
+    }
+
+    pub fn radians_to_degrees(&self, radians: f64) -> f64 {
+        radians * 180.0 / PI
+    }
+
+    /// Factorial as an `f64`, extended to real numbers as x! = Γ(x + 1).
+    /// Integers above 170! overflow; use `exact_factorial`.
+    pub fn factorial(&self, n: f64) -> Result<f64, CalcError> {
+        if n < 0.0 && n == n.floor() {
+            return Err(CalcError::Domain("Factorial is undefined for negative integers".to_string()));
+        }
+        if n != n.floor() {
+            return finite(special::gamma(n + 1.0), "Factorial overflowed");
+        }
+        if n > MAX_F64_FACTORIAL {
+            return Err(CalcError::Overflow(format!(
+                "{}! is too large for a floating-point result; use exact mode",
+                n
+            )));
+        }
+        let n = BigInt::from_f64(n).ok_or_else(|| CalcError::Domain(format!("{} is not an integer", n)))?;
+        self.exact_factorial(&n).map(|result| result.to_f64())
+    }
+
+    /// The gamma function, Γ(n) = (n - 1)! for positive integers.
+    pub fn gamma(&self, x: f64) -> Result<f64, CalcError> {
+        if x <= 0.0 && x == x.floor() {
+            return Err(CalcError::Domain(
+                "Gamma is undefined at zero and the negative integers".to_string(),
+            ));
+        }
+        if x == x.floor() && x <= MAX_F64_FACTORIAL + 1.0 {
+            return self.factorial(x - 1.0);
+        }
+        finite(special::gamma(x), "Gamma overflowed; use lgamma")
+    }
+
+    /// The natural logarithm of |Γ(x)|, for arguments whose gamma would overflow.
+    pub fn ln_gamma(&self, x: f64) -> Result<f64, CalcError> {
+        if x <= 0.0 && x == x.floor() {
+            return Err(CalcError::Domain(
+                "Gamma is undefined at zero and the negative integers".to_string(),
+            ));
+        }
+        finite(special::ln_gamma(x), "Log-gamma overflowed")
+    }
+
+    /// The beta function B(a, b) = Γ(a)·Γ(b) / Γ(a + b).
+    pub fn beta(&self, a: f64, b: f64) -> Result<f64, CalcError> {
+        if [a, b].iter().any(|&x| x <= 0.0 && x == x.floor()) {
+            return Err(CalcError::Domain(
+                "Beta is undefined when either argument is zero or a negative integer".to_string(),
+            ));
+        }
+        if a == a.floor() && b == b.floor() && a + b <= MAX_F64_FACTORIAL + 1.0 {
+            return Ok(self.gamma(a)? * self.gamma(b)? / self.gamma(a + b)?);
+        }
+        finite(special::beta(a, b), "Beta overflowed")
+    }
+
+    pub fn exact_add(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        a + b
+    }
+
+    pub fn exact_subtract(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        a - b
+    }
+
+    pub fn exact_multiply(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        a * b
+    }
+
+    /// Integer power with arbitrary precision, capped at `MAX_EXACT_DIGITS` digits.
+    pub fn exact_power(&self, base: &BigInt, exponent: &BigInt) -> Result<BigInt, CalcError> {
+        if exponent.is_negative() {
+            return Err(CalcError::Domain(
+                "Exact power requires a non-negative integer exponent".to_string(),
+            ));
+        }
+        let too_large = || CalcError::Overflow("Exact power result is too large".to_string());
+        let mut exponent = exponent.to_u64().ok_or_else(too_large)?;
+        if base.to_f64().abs() <= 1.0 {
+            // 0, 1 and -1 stay put for any exponent; only its parity matters
+            exponent = exponent.min(2 - exponent % 2);
+        } else if (base.digit_count() as u64).checked_mul(exponent).is_none_or(|digits| digits > MAX_EXACT_DIGITS) {
+            return Err(too_large());
+        }
+        let exponent = u32::try_from(exponent).map_err(|_| too_large())?;
+        Ok(base.pow(exponent))
+    }
+
+    /// `n!` with arbitrary precision, for `n` up to `MAX_EXACT_FACTORIAL`.
+    pub fn exact_factorial(&self, n: &BigInt) -> Result<BigInt, CalcError> {
+        if n.is_negative() {
+            return Err(CalcError::Domain("Factorial is undefined for negative integers".to_string()));
+        }
+        let n = n
+            .to_u64()
+            .filter(|&n| n <= MAX_EXACT_FACTORIAL)
+            .ok_or_else(|| CalcError::Overflow(format!("Exact factorial is limited to {}!", MAX_EXACT_FACTORIAL)))?;
+
+        let mut result = BigInt::from_u64(1);
+        for k in 2..=n {
+            result.mul_small(k as u32);
+        }
+        Ok(result)
+    }
+
+    /// n!! = n·(n - 2)·(n - 4)···, for integers n ≥ -1.
+    pub fn double_factorial(&self, n: f64) -> Result<f64, CalcError> {
+        let n = integer_argument(n, "Double factorial")?;
+        to_finite(self.exact_double_factorial(&n)?, "Double factorial overflowed")
+    }
+
+    /// Ways to choose r of n items, C(n, r). A real n with a whole r is a
+    /// falling product; other non-integers use the gamma function.
+    pub fn combinations(&self, n: f64, r: f64) -> Result<f64, CalcError> {
+        match (BigInt::from_f64(n), BigInt::from_f64(r)) {
+            (Some(n), Some(r)) => to_finite(self.exact_combinations(&n, &r)?, "nCr overflowed"),
+            _ if is_small_count(r) => {
+                let result = (1..=r as u64).fold(1.0, |product, i| product * (n - r + i as f64) / i as f64);
+                finite(result, "nCr overflowed")
+            }
+            _ => {
+                let result = special::gamma(n + 1.0) / (special::gamma(r + 1.0) * special::gamma(n - r + 1.0));
+                finite(result, "nCr overflowed")
+            }
+        }
+    }
+
+    /// Ordered arrangements of r of n items, P(n, r). A real n with a whole
+    /// r is a falling product; other non-integers use the gamma function.
+    pub fn permutations(&self, n: f64, r: f64) -> Result<f64, CalcError> {
+        match (BigInt::from_f64(n), BigInt::from_f64(r)) {
+            (Some(n), Some(r)) => to_finite(self.exact_permutations(&n, &r)?, "nPr overflowed"),
+            _ if is_small_count(r) => {
+                let result = (1..=r as u64).fold(1.0, |product, i| product * (n - r + i as f64));
+                finite(result, "nPr overflowed")
+            }
+            _ => finite(special::gamma(n + 1.0) / special::gamma(n - r + 1.0), "nPr overflowed"),
+        }
+    }
+
+    /// (k1 + k2 + …)! / (k1!·k2!···), the ways to split items into groups of those sizes.
+    pub fn multinomial(&self, counts: &[f64]) -> Result<f64, CalcError> {
+        let counts = counts
+            .iter()
+            .map(|&k| integer_argument(k, "Multinomial"))
+            .collect::<Result<Vec<_>, _>>()?;
+        to_finite(self.exact_multinomial(&counts)?, "Multinomial overflowed")
+    }
+
+    /// The nth Catalan number, C(2n, n) / (n + 1).
+    pub fn catalan(&self, n: f64) -> Result<f64, CalcError> {
+        let n = integer_argument(n, "Catalan")?;
+        to_finite(self.exact_catalan(&n)?, "Catalan number overflowed")
+    }
+
+    /// Unsigned Stirling number of the first kind: permutations of n items with k cycles.
+    pub fn stirling_first(&self, n: f64, k: f64) -> Result<f64, CalcError> {
+        let (n, k) = (integer_argument(n, "Stirling")?, integer_argument(k, "Stirling")?);
+        to_finite(self.exact_stirling_first(&n, &k)?, "Stirling number overflowed")
+    }
+
+    /// Stirling number of the second kind: partitions of n items into k non-empty sets.
+    pub fn stirling_second(&self, n: f64, k: f64) -> Result<f64, CalcError> {
+        let (n, k) = (integer_argument(n, "Stirling")?, integer_argument(k, "Stirling")?);
+        to_finite(self.exact_stirling_second(&n, &k)?, "Stirling number overflowed")
+    }
+
+    /// `n!!` with arbitrary precision; `(-1)!!` and `0!!` are 1.
+    pub fn exact_double_factorial(&self, n: &BigInt) -> Result<BigInt, CalcError> {
+        if n == &-BigInt::from_u64(1) {
+            return Ok(BigInt::from_u64(1));
+        }
+        if n.is_negative() {
+            return Err(CalcError::Domain("Double factorial is only defined for integers n ≥ -1".to_string()));
+        }
+        let n = exact_count(n, "Double factorial", MAX_EXACT_FACTORIAL)?;
+        let mut result = BigInt::from_u64(1);
+        for k in (2..=n).rev().step_by(2) {
+            result.mul_small(k as u32);
+        }
+        Ok(result)
+    }
+
+    /// C(n, r) with arbitrary precision; zero when r > n.
+    pub fn exact_combinations(&self, n: &BigInt, r: &BigInt) -> Result<BigInt, CalcError> {
+        let n = exact_count(n, "nCr", u32::MAX as u64)?;
+        let r = exact_count(r, "nCr", u32::MAX as u64)?;
+        if r > n {
+            return Ok(BigInt::zero());
+        }
+        binomial(n, r.min(n - r))
+    }
+
+    /// P(n, r) with arbitrary precision; zero when r > n.
+    pub fn exact_permutations(&self, n: &BigInt, r: &BigInt) -> Result<BigInt, CalcError> {
+        let n = exact_count(n, "nPr", u32::MAX as u64)?;
+        let r = exact_count(r, "nPr", MAX_EXACT_FACTORIAL)?;
+        if r > n {
+            return Ok(BigInt::zero());
+        }
+        let mut result = BigInt::from_u64(1);
+        for k in n - r + 1..=n {
+            result.mul_small(k as u32);
+        }
+        Ok(result)
+    }
+
+    /// The multinomial coefficient with arbitrary precision, built up as a
+    /// product of binomial coefficients.
+    pub fn exact_multinomial(&self, counts: &[BigInt]) -> Result<BigInt, CalcError> {
+        let mut total = 0;
+        let mut result = BigInt::from_u64(1);
+        for count in counts {
+            let count = exact_count(count, "Multinomial", MAX_EXACT_FACTORIAL)?;
+            total += count;
+            if total > MAX_EXACT_FACTORIAL {
+                return Err(CalcError::Overflow(format!(
+                    "Multinomial coefficients are limited to {} items in total",
+                    MAX_EXACT_FACTORIAL
+                )));
+            }
+            result = &result * &binomial(total, count.min(total - count))?;
+        }
+        Ok(result)
+    }
+
+    pub fn exact_catalan(&self, n: &BigInt) -> Result<BigInt, CalcError> {
+        let n = exact_count(n, "Catalan", MAX_EXACT_FACTORIAL)?;
+        let mut result = binomial(2 * n, n)?;
+        result.div_small(n as u32 + 1);
+        Ok(result)
+    }
+
+    pub fn exact_stirling_first(&self, n: &BigInt, k: &BigInt) -> Result<BigInt, CalcError> {
+        let n = exact_count(n, "Stirling", MAX_EXACT_STIRLING)?;
+        let k = exact_count(k, "Stirling", MAX_EXACT_STIRLING)?;
+        Ok(stirling(n, k, |i, _| i - 1))
+    }
+
+    pub fn exact_stirling_second(&self, n: &BigInt, k: &BigInt) -> Result<BigInt, CalcError> {
+        let n = exact_count(n, "Stirling", MAX_EXACT_STIRLING)?;
+        let k = exact_count(k, "Stirling", MAX_EXACT_STIRLING)?;
+        Ok(stirling(n, k, |_, j| j))
+    }
+
+    pub fn complex_add(&self, a: Complex, b: Complex) -> Result<Complex, CalcError> {
+        finite_complex(a + b, "Addition overflowed")
+    }
+
+    pub fn complex_subtract(&self, a: Complex, b: Complex) -> Result<Complex, CalcError> {
+        finite_complex(a - b, "Subtraction overflowed")
+    }
+
+    pub fn complex_multiply(&self, a: Complex, b: Complex) -> Result<Complex, CalcError> {
+        finite_complex(a * b, "Multiplication overflowed")
+    }
+
+    pub fn complex_divide(&self, a: Complex, b: Complex) -> Result<Complex, CalcError> {
+        if b.is_zero() {
+            Err(CalcError::DivisionByZero)
+        } else {
+            finite_complex(a / b, "Division overflowed")
+        }
+    }
+
+    /// Principal value of `base ^ exponent`.
+    pub fn complex_power(&self, base: Complex, exponent: Complex) -> Result<Complex, CalcError> {
+        if base.is_zero() {
+            return match exponent {
+                e if e.is_zero() => Ok(Complex::from(1.0)),
+                e if e.re > 0.0 => Ok(Complex::from(0.0)),
+                _ => Err(CalcError::DivisionByZero),
+            };
+        }
+        // Small integer exponents by repeated squaring, so that i^2 is exactly -1
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= 64.0 {
+            let mut n = exponent.re.abs() as u32;
+            let (mut result, mut square) = (Complex::from(1.0), base);
+            while n > 0 {
+                if n & 1 == 1 {
+                    result = result * square;
+                }
+                square = square * square;
+                n >>= 1;
+            }
+            let result = if exponent.re < 0.0 { Complex::from(1.0) / result } else { result };
+            return finite_complex(result, "Power overflowed");
+        }
+        finite_complex(base.powc(exponent), "Power overflowed")
+    }
+
+    /// Principal square root, so `sqrt(-4)` is `2i`.
+    pub fn complex_sqrt(&self, z: Complex) -> Complex {
+        z.sqrt()
+    }
+
+    pub fn complex_ln(&self, z: Complex) -> Result<Complex, CalcError> {
+        if z.is_zero() {
+            Err(CalcError::Domain("Logarithm of zero is undefined".to_string()))
+        } else {
+            Ok(z.ln())
+        }
+    }
+
+    pub fn complex_log10(&self, z: Complex) -> Result<Complex, CalcError> {
+        self.complex_ln(z).map(|ln| ln.scale(1.0 / std::f64::consts::LN_10))
+    }
+
+    /// Complex sine of `z` in radians.
+    pub fn complex_sin(&self, z: Complex) -> Complex {
+        z.sin()
+    }
+
+    pub fn complex_cos(&self, z: Complex) -> Complex {
+        z.cos()
+    }
+
+    /// Complex tangent of `z` in radians. Its poles all lie on the real axis,
+    /// where cos(z) never comes out exactly zero, so real arguments go through
+    /// [`Calculator::tan`] and its tolerance instead.
+    pub fn complex_tan(&self, z: Complex) -> Result<Complex, CalcError> {
+        if z.im == 0.0 {
+            return self.tan(z.re, AngleUnit::Radians).map(Complex::from);
+        }
+        finite_complex(z.sin() / z.cos(), "Tangent overflowed")
+    }
+
+    /// Every memory register's contents.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// MS: replaces a register. Memory changes are recorded in the history.
+    pub fn store_memory(&mut self, register: Register, value: f64) {
+        self.memory.set(register, value);
+        self.record_memory("memory_store", format!("Stored {} in {}", value, register));
+    }
+
+    /// MR: a register's value.
+    pub fn recall_memory(&self, register: Register) -> f64 {
+        self.memory.get(register)
+    }
+
+    /// M+: adds to a register.
+    pub fn add_to_memory(&mut self, register: Register, value: f64) {
+        self.memory.set(register, self.memory.get(register) + value);
+        self.record_memory("memory_add", format!("Added {} to {}", value, register));
+    }
+
+    /// M-: subtracts from a register.
+    pub fn subtract_from_memory(&mut self, register: Register, value: f64) {
+        self.memory.set(register, self.memory.get(register) - value);
+        self.record_memory("memory_subtract", format!("Subtracted {} from {}", value, register));
+    }
+
+    /// MC: resets a register to zero.
+    pub fn clear_memory(&mut self, register: Register) {
+        self.memory.set(register, 0.0);
+        self.record_memory("memory_clear", format!("Cleared {}", register));
+    }
+
+    pub fn clear_all_memory(&mut self) {
+        self.memory.clear();
+        self.record_memory("memory_clear", "Cleared all memory".to_string());
+    }
+
+    fn record_memory(&mut self, operation: &str, description: String) {
+        self.add_to_history(HistoryEntry::success(operation, &description, None));
+    }
+
+    /// Records an entry, dropping the oldest once the history is at capacity.
+    pub fn add_to_history(&mut self, entry: HistoryEntry) {
+        self.history.push(entry);
+    }
+}
+
+fn finite_complex(result: Complex, message: &str) -> Result<Complex, CalcError> {
+    if result.is_finite() {
+        Ok(result)
+    } else {
+        Err(CalcError::Overflow(message.to_string()))
+    }
+}
+
+const ANSWER: &str = "ans";
+const RESERVED_NAMES: [&str; 3] = ["pi", "e", ANSWER];
+const MAX_F64_FACTORIAL: f64 = 170.0;
+const MAX_EXACT_FACTORIAL: u64 = 10_000;
+const MAX_EXACT_STIRLING: u64 = 1_000;
+const MAX_EXACT_DIGITS: u64 = 100_000;
+
+fn finite(result: f64, message: &str) -> Result<f64, CalcError> {
+    if !result.is_finite() {
+        Err(CalcError::Overflow(message.to_string()))
+    } else {
+        Ok(result)
+    }
+}
+
+/// An integral `f64` as a `BigInt`, for the integer-only combinatorics.
+fn integer_argument(x: f64, name: &str) -> Result<BigInt, CalcError> {
+    BigInt::from_f64(x).ok_or_else(|| CalcError::Domain(format!("{} is only defined for integers", name)))
+}
+
+/// Whether `r` is a whole number of terms small enough to multiply out.
+fn is_small_count(r: f64) -> bool {
+    r >= 0.0 && r == r.floor() && r <= MAX_EXACT_FACTORIAL as f64
+}
+
+fn to_finite(result: BigInt, message: &str) -> Result<f64, CalcError> {
+    finite(result.to_f64(), message)
+}
+
+/// A non-negative integer argument as a `u64`, at most `limit`.
+fn exact_count(n: &BigInt, name: &str, limit: u64) -> Result<u64, CalcError> {
+    if n.is_negative() {
+        return Err(CalcError::Domain(format!("{} is only defined for non-negative integers", name)));
+    }
+    n.to_u64()
+        .filter(|&n| n <= limit)
+        .ok_or_else(|| CalcError::Overflow(format!("Exact {} is limited to arguments up to {}", name, limit)))
+}
+
+/// C(n, r) for r ≤ n - r, multiplying and dividing term by term so every
+/// intermediate value is itself a binomial coefficient.
+fn binomial(n: u64, r: u64) -> Result<BigInt, CalcError> {
+    if r > MAX_EXACT_FACTORIAL {
+        return Err(CalcError::Overflow(format!(
+            "Exact binomial coefficients are limited to choosing {}",
+            MAX_EXACT_FACTORIAL
+        )));
+    }
+    let mut result = BigInt::from_u64(1);
+    for i in 1..=r {
+        result.mul_small((n - r + i) as u32);
+        result.div_small(i as u32);
+    }
+    Ok(result)
+}
+
+/// Stirling numbers by their recurrence, row by row:
+/// s(i, j) = weight(i, j)·s(i - 1, j) + s(i - 1, j - 1).
+fn stirling(n: u64, k: u64, weight: impl Fn(u64, u64) -> u64) -> BigInt {
+    if k > n {
+        return BigInt::zero();
+    }
+    let mut row = vec![BigInt::zero(); k as usize + 1];
+    row[0] = BigInt::from_u64(1);
+    for i in 1..=n {
+        for j in (1..=k.min(i)).rev() {
+            let mut term = row[j as usize].clone();
+            term.mul_small(weight(i, j) as u32);
+            row[j as usize] = &term + &row[j as usize - 1];
+        }
+        row[0] = BigInt::zero();
+    }
+    row.swap_remove(k as usize)
+}
@@ -0,0 +1,124 @@
+use calculator::{history, AngleUnit};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: hello-ngrok [COMMAND]
+
+Commands:
+  serve [--host HOST] [--port PORT] [HISTORY]   Start the web calculator (default)
+  repl [HISTORY]                                Start the interactive calculator
+  eval [--angle-unit UNIT] <EXPR>               Evaluate one expression and print the result
+  help                                          Show this message
+
+HISTORY options:
+  --history-file PATH   Where history is kept across restarts (default history.jsonl)
+  --no-history-file     Keep history in memory only
+  --history-size N      Entries kept per session (default 10)
+
+UNIT is one of degrees, radians or gradians.";
+
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_HISTORY_FILE: &str = "history.jsonl";
+
+/// Where history is persisted and how much of it each calculator keeps.
+#[derive(Debug, PartialEq)]
+pub struct HistoryOptions {
+    pub file: Option<PathBuf>,
+    pub capacity: usize,
+}
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        HistoryOptions {
+            file: Some(PathBuf::from(DEFAULT_HISTORY_FILE)),
+            capacity: history::DEFAULT_CAPACITY,
+        }
+    }
+}
+
+impl HistoryOptions {
+    /// Consumes `flag` and its value if it is a history option.
+    fn parse_flag(&mut self, flag: &str, args: &mut impl Iterator<Item = String>) -> Result<bool, String> {
+        match flag {
+            "--history-file" => self.file = Some(PathBuf::from(flag_value(flag, args.next())?)),
+            "--no-history-file" => self.file = None,
+            "--history-size" => {
+                let value = flag_value(flag, args.next())?;
+                self.capacity = value.parse().map_err(|_| format!("Invalid history size '{}'", value))?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve { host: String, port: u16, history: HistoryOptions },
+    Repl { history: HistoryOptions },
+    Eval { expression: String, angle_unit: AngleUnit },
+    Help,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let Some(command) = args.next() else {
+        return Ok(Command::Serve {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            history: HistoryOptions::default(),
+        });
+    };
+
+    match command.as_str() {
+        "serve" => {
+            let mut host = DEFAULT_HOST.to_string();
+            let mut port = DEFAULT_PORT;
+            let mut history = HistoryOptions::default();
+            while let Some(flag) = args.next() {
+                match flag.as_str() {
+                    "--host" => host = flag_value(&flag, args.next())?,
+                    "--port" => {
+                        let value = flag_value(&flag, args.next())?;
+                        port = value.parse().map_err(|_| format!("Invalid port '{}'", value))?;
+                    }
+                    _ if history.parse_flag(&flag, &mut args)? => {}
+                    _ => return Err(format!("Unknown option '{}' for serve", flag)),
+                }
+            }
+            Ok(Command::Serve { host, port, history })
+        }
+        "repl" => {
+            let mut history = HistoryOptions::default();
+            while let Some(flag) = args.next() {
+                if !history.parse_flag(&flag, &mut args)? {
+                    return Err(format!("Unexpected argument '{}' for repl", flag));
+                }
+            }
+            Ok(Command::Repl { history })
+        }
+        "eval" => {
+            let mut angle_unit = AngleUnit::default();
+            let mut expression: Option<String> = None;
+            while let Some(arg) = args.next() {
+                if arg == "--angle-unit" {
+                    let value = flag_value(&arg, args.next())?;
+                    angle_unit = value.parse()?;
+                } else if expression.is_none() {
+                    expression = Some(arg);
+                } else {
+                    return Err("eval takes a single expression; quote it".to_string());
+                }
+            }
+            let expression = expression.ok_or("eval needs an expression")?;
+            Ok(Command::Eval { expression, angle_unit })
+        }
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => Err(format!("Unknown command '{}'", command)),
+    }
+}
+
+fn flag_value(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("Option '{}' needs a value", flag))
+}
@@ -0,0 +1,469 @@
+use std::f64::consts::{PI, SQRT_2};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::angle::AngleUnit;
+use crate::error::CalcError;
+use crate::registry::Operator;
+use crate::special;
+use crate::Calculator;
+
+/// The probability distributions the calculator knows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Distribution {
+    Normal,
+    StudentT,
+    ChiSquared,
+    F,
+    Binomial,
+    Poisson,
+    Exponential,
+    Uniform,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 8] = [
+        Distribution::Normal,
+        Distribution::StudentT,
+        Distribution::ChiSquared,
+        Distribution::F,
+        Distribution::Binomial,
+        Distribution::Poisson,
+        Distribution::Exponential,
+        Distribution::Uniform,
+    ];
+
+    /// Whether the distribution is over the integers, with a mass function
+    /// instead of a density.
+    pub fn is_discrete(self) -> bool {
+        matches!(self, Distribution::Binomial | Distribution::Poisson)
+    }
+
+    /// Probability density, or mass for discrete distributions, at `x`.
+    pub fn density(self, x: f64, params: &[f64]) -> f64 {
+        let (a, b) = parameters(params);
+        match self {
+            Distribution::Normal => {
+                let z = (x - a) / b;
+                (-0.5 * z * z).exp() / (b * (2.0 * PI).sqrt())
+            }
+            Distribution::StudentT => {
+                let ln_scale = special::ln_gamma((a + 1.0) / 2.0) - special::ln_gamma(a / 2.0);
+                ln_scale.exp() / (a * PI).sqrt() * (1.0 + x * x / a).powf(-(a + 1.0) / 2.0)
+            }
+            Distribution::ChiSquared => match x {
+                x if x < 0.0 => 0.0,
+                0.0 if a < 2.0 => f64::INFINITY,
+                0.0 if a == 2.0 => 0.5,
+                0.0 => 0.0,
+                x => ((a / 2.0 - 1.0) * x.ln() - x / 2.0 - a / 2.0 * 2f64.ln() - special::ln_gamma(a / 2.0)).exp(),
+            },
+            Distribution::F => match x {
+                x if x < 0.0 => 0.0,
+                0.0 if a < 2.0 => f64::INFINITY,
+                0.0 if a == 2.0 => 1.0,
+                0.0 => 0.0,
+                x => {
+                    let ln_numerator = 0.5 * (a * (a * x).ln() + b * b.ln() - (a + b) * (a * x + b).ln());
+                    (ln_numerator - x.ln() - special::ln_beta(a / 2.0, b / 2.0)).exp()
+                }
+            },
+            Distribution::Binomial => {
+                if x < 0.0 || x > a || x != x.floor() {
+                    0.0
+                } else if b == 0.0 || b == 1.0 {
+                    // All the mass is on 0 or on n
+                    let certain = if b == 0.0 { 0.0 } else { a };
+                    if x == certain { 1.0 } else { 0.0 }
+                } else if a <= MAX_DIRECT_TRIALS {
+                    choose(a, x) * b.powf(x) * (1.0 - b).powf(a - x)
+                } else {
+                    let ln_choose =
+                        special::ln_gamma(a + 1.0) - special::ln_gamma(x + 1.0) - special::ln_gamma(a - x + 1.0);
+                    (ln_choose + x * b.ln() + (a - x) * (-b).ln_1p()).exp()
+                }
+            }
+            Distribution::Poisson => {
+                if x < 0.0 || x != x.floor() {
+                    0.0
+                } else {
+                    (x * a.ln() - a - special::ln_gamma(x + 1.0)).exp()
+                }
+            }
+            Distribution::Exponential if x < 0.0 => 0.0,
+            Distribution::Exponential => a * (-a * x).exp(),
+            Distribution::Uniform if (a..=b).contains(&x) => 1.0 / (b - a),
+            Distribution::Uniform => 0.0,
+        }
+    }
+
+    /// Probability of a value at most `x`. Fails only if the special
+    /// function behind it does not converge.
+    pub fn cumulative(self, x: f64, params: &[f64]) -> Result<f64, CalcError> {
+        let (a, b) = parameters(params);
+        let probability = match self {
+            Distribution::Normal => 0.5 * special::erfc(-(x - a) / (b * SQRT_2))?,
+            Distribution::StudentT => {
+                // Near the centre a / (a + x²) rounds to 1, so measure from
+                // the middle instead of from the tail there
+                let t = x * x / (a + x * x);
+                if t < 0.5 {
+                    0.5 + 0.5 * x.signum() * special::beta_regularized(t, 0.5, a / 2.0)?
+                } else {
+                    let tail = 0.5 * student_t_tail(x, a)?;
+                    if x > 0.0 { 1.0 - tail } else { tail }
+                }
+            }
+            Distribution::ChiSquared => special::gamma_p(a / 2.0, x.max(0.0) / 2.0)?,
+            Distribution::F if x <= 0.0 => 0.0,
+            Distribution::F => {
+                // Far out a x / (a x + b) rounds to 1, so take the upper
+                // tail from its complement there
+                let ratio = (b / a) / (b / a + x);
+                if ratio < 0.5 {
+                    1.0 - special::beta_regularized(ratio, b / 2.0, a / 2.0)?
+                } else {
+                    special::beta_regularized(a * x / (a * x + b), a / 2.0, b / 2.0)?
+                }
+            }
+            Distribution::Binomial => {
+                let k = x.floor();
+                if k < 0.0 {
+                    0.0
+                } else if k >= a {
+                    1.0
+                } else if a <= MAX_DIRECT_TRIALS {
+                    (0..=k as u32).map(|i| self.density(i as f64, params)).sum::<f64>().min(1.0)
+                } else {
+                    special::beta_regularized(1.0 - b, a - k, k + 1.0)?
+                }
+            }
+            Distribution::Poisson if x < 0.0 => 0.0,
+            Distribution::Poisson => special::gamma_q(x.floor() + 1.0, a)?,
+            Distribution::Exponential if x < 0.0 => 0.0,
+            Distribution::Exponential => -(-a * x).exp_m1(),
+            Distribution::Uniform => ((x - a) / (b - a)).clamp(0.0, 1.0),
+        };
+        Ok(probability)
+    }
+
+    /// The smallest value whose cumulative probability is at least `p`.
+    /// Probabilities of 0 or 1 at an unbounded end of the support would
+    /// give an infinite value; the operations reject them beforehand.
+    pub fn quantile(self, p: f64, params: &[f64]) -> Result<f64, CalcError> {
+        let (a, b) = parameters(params);
+        let cumulative = |x: f64| self.cumulative(x, params);
+        match self {
+            Distribution::Exponential => Ok(-(-p).ln_1p() / a),
+            Distribution::Uniform => Ok(a + p * (b - a)),
+            Distribution::Binomial => integer_quantile(cumulative, p, Some(a)),
+            Distribution::Poisson => integer_quantile(cumulative, p, None),
+            Distribution::ChiSquared | Distribution::F if p == 0.0 => Ok(0.0),
+            Distribution::ChiSquared | Distribution::F => bisect(cumulative, p, 0.0),
+            // Symmetric about the centre, where rounding would otherwise
+            // leave the bisection a hair off
+            Distribution::Normal if p == 0.5 => Ok(a),
+            Distribution::StudentT if p == 0.5 => Ok(0.0),
+            Distribution::Normal | Distribution::StudentT => {
+                // Bracket the quantile by doubling outwards from zero
+                let lower = bracket(&cumulative, -1.0, |probability| probability >= p)?;
+                bisect(cumulative, p, lower)
+            }
+        }
+    }
+
+    /// Rejects parameters outside the distribution's domain.
+    fn check(self, params: &[f64]) -> Result<(), String> {
+        let (a, b) = parameters(params);
+        let ok = match self {
+            Distribution::Normal => b > 0.0,
+            Distribution::StudentT | Distribution::ChiSquared | Distribution::Exponential => a > 0.0,
+            Distribution::Poisson => a > 0.0 && a <= MAX_COUNT,
+            Distribution::F => a > 0.0 && b > 0.0,
+            Distribution::Binomial => (0.0..=MAX_COUNT).contains(&a) && a == a.floor() && (0.0..=1.0).contains(&b),
+            Distribution::Uniform => a < b,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(self.parameter_domain().to_string())
+        }
+    }
+
+    fn parameter_domain(self) -> &'static str {
+        match self {
+            Distribution::Normal => "standard deviation > 0",
+            Distribution::StudentT | Distribution::ChiSquared => "degrees of freedom > 0",
+            Distribution::F => "both degrees of freedom > 0",
+            Distribution::Binomial => "trials an integer from 0 to 2^53; 0 ≤ success probability ≤ 1",
+            Distribution::Poisson => "0 < rate ≤ 2^53",
+            Distribution::Exponential => "rate > 0",
+            Distribution::Uniform => "lower bound < upper bound",
+        }
+    }
+
+    /// Whether the quantile is finite at `p`: unbounded sides of the
+    /// support are only reached at probability 0 or 1.
+    fn quantile_defined(self, p: f64) -> bool {
+        match self {
+            Distribution::Normal | Distribution::StudentT => p > 0.0 && p < 1.0,
+            Distribution::ChiSquared | Distribution::F | Distribution::Poisson | Distribution::Exponential => {
+                (0.0..1.0).contains(&p)
+            }
+            Distribution::Binomial | Distribution::Uniform => (0.0..=1.0).contains(&p),
+        }
+    }
+}
+
+/// Largest binomial trial count or Poisson rate. Beyond 2^53 counts are no
+/// longer exact in an `f64`, so neither are the distributions' integers.
+const MAX_COUNT: f64 = 9_007_199_254_740_992.0;
+
+/// Binomial distributions up to this many trials are computed term by
+/// term, which is exact for simple probabilities; larger ones go through
+/// the gamma and beta functions.
+const MAX_DIRECT_TRIALS: f64 = 1000.0;
+
+/// n choose k for integers 0 ≤ k ≤ n, as a running product.
+fn choose(n: f64, k: f64) -> f64 {
+    let k = k.min(n - k) as u32;
+    (1..=k).fold(1.0, |product, i| product * (n - k as f64 + i as f64) / i as f64)
+}
+
+/// Probability that Student's t with `a` degrees of freedom lies beyond ±x,
+/// I_r(a/2, 1/2) with r = a / (a + x²). With few degrees of freedom the tail
+/// is still sizeable where x² overflows, so once r is below machine epsilon
+/// the leading term of its series, exact to `f64` there, is used instead.
+fn student_t_tail(x: f64, a: f64) -> Result<f64, CalcError> {
+    let ratio = a / (a + x * x);
+    if ratio >= f64::EPSILON {
+        return special::beta_regularized(ratio, a / 2.0, 0.5);
+    }
+    let ln_ratio = a.ln() - 2.0 * x.abs().ln();
+    Ok((a / 2.0 * ln_ratio - (a / 2.0).ln() - special::ln_beta(a / 2.0, 0.5)).exp())
+}
+
+/// The first two parameters, which is all any distribution has.
+fn parameters(params: &[f64]) -> (f64, f64) {
+    (params.first().copied().unwrap_or_default(), params.get(1).copied().unwrap_or_default())
+}
+
+/// Doubles `bound` for as long as `short` holds of its cumulative
+/// probability. Fails once the bound or its probability stops being finite,
+/// which for heavy tails means the quantile is beyond `f64`.
+fn bracket(
+    cumulative: &impl Fn(f64) -> Result<f64, CalcError>,
+    mut bound: f64,
+    short: impl Fn(f64) -> bool,
+) -> Result<f64, CalcError> {
+    loop {
+        let probability = cumulative(bound)?;
+        if !bound.is_finite() || !probability.is_finite() {
+            return Err(CalcError::Overflow("The quantile is too large to represent".to_string()));
+        }
+        if !short(probability) {
+            return Ok(bound);
+        }
+        bound *= 2.0;
+    }
+}
+
+/// Finds where an increasing `cumulative` crosses `p`, above `lower`,
+/// to full `f64` precision.
+fn bisect(cumulative: impl Fn(f64) -> Result<f64, CalcError>, p: f64, mut lower: f64) -> Result<f64, CalcError> {
+    let mut upper = bracket(&cumulative, 1.0, |probability| probability < p)?;
+    loop {
+        let middle = lower + (upper - lower) / 2.0;
+        if middle <= lower || middle >= upper {
+            return Ok(upper);
+        }
+        if cumulative(middle)? < p {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+}
+
+/// The smallest non-negative integer `k` with `cumulative(k) ≥ p`, at most
+/// `max` when the support is bounded. Allows for rounding in `cumulative`
+/// so that `p` taken from a cumulative probability maps back to its `k`.
+fn integer_quantile(
+    cumulative: impl Fn(f64) -> Result<f64, CalcError>,
+    p: f64,
+    max: Option<f64>,
+) -> Result<f64, CalcError> {
+    let target = p * (1.0 - 64.0 * f64::EPSILON);
+    let mut upper = max.unwrap_or(1.0);
+    while max.is_none() && cumulative(upper)? < target {
+        upper *= 2.0;
+    }
+    let mut lower = 0.0;
+    if cumulative(lower)? >= target {
+        return Ok(lower);
+    }
+    // Invariant: cumulative(lower) < target ≤ cumulative(upper)
+    while upper - lower > 1.0 {
+        let middle = ((lower + upper) / 2.0).floor();
+        // Past 2^53 neighbouring floats are more than 1 apart
+        if middle <= lower || middle >= upper {
+            break;
+        }
+        if cumulative(middle)? < target {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    Ok(upper)
+}
+
+/// Which function of a distribution an operation computes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// The probability density, or mass for discrete distributions.
+    Density,
+    Cumulative,
+    /// The inverse of the cumulative distribution function.
+    Quantile,
+}
+
+/// One function of one distribution, registered by default as an operation
+/// such as `normal_cdf(x, mean, sd)` or `t_inv(p, df)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DistributionOperation {
+    pub distribution: Distribution,
+    pub kind: Kind,
+}
+
+impl DistributionOperation {
+    /// Every distribution's density, cumulative and quantile functions.
+    pub fn all() -> impl Iterator<Item = DistributionOperation> {
+        Distribution::ALL.into_iter().flat_map(|distribution| {
+            [Kind::Density, Kind::Cumulative, Kind::Quantile]
+                .map(|kind| DistributionOperation { distribution, kind })
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        use Distribution::*;
+        use Kind::*;
+        match (self.distribution, self.kind) {
+            (Normal, Density) => "normal_pdf",
+            (Normal, Cumulative) => "normal_cdf",
+            (Normal, Quantile) => "normal_inv",
+            (StudentT, Density) => "t_pdf",
+            (StudentT, Cumulative) => "t_cdf",
+            (StudentT, Quantile) => "t_inv",
+            (ChiSquared, Density) => "chi2_pdf",
+            (ChiSquared, Cumulative) => "chi2_cdf",
+            (ChiSquared, Quantile) => "chi2_inv",
+            (F, Density) => "f_pdf",
+            (F, Cumulative) => "f_cdf",
+            (F, Quantile) => "f_inv",
+            (Binomial, Density) => "binomial_pmf",
+            (Binomial, Cumulative) => "binomial_cdf",
+            (Binomial, Quantile) => "binomial_inv",
+            (Poisson, Density) => "poisson_pmf",
+            (Poisson, Cumulative) => "poisson_cdf",
+            (Poisson, Quantile) => "poisson_inv",
+            (Exponential, Density) => "exponential_pdf",
+            (Exponential, Cumulative) => "exponential_cdf",
+            (Exponential, Quantile) => "exponential_inv",
+            (Uniform, Density) => "uniform_pdf",
+            (Uniform, Cumulative) => "uniform_cdf",
+            (Uniform, Quantile) => "uniform_inv",
+        }
+    }
+}
+
+impl Operator for DistributionOperation {
+    fn name(&self) -> &str {
+        DistributionOperation::name(*self)
+    }
+
+    /// The value (or probability, for quantiles) followed by the parameters.
+    fn operands(&self) -> &[&'static str] {
+        let quantile = self.kind == Kind::Quantile;
+        match (self.distribution, quantile) {
+            (Distribution::Normal, false) => &["x", "mean", "standard deviation"],
+            (Distribution::Normal, true) => &["probability", "mean", "standard deviation"],
+            (Distribution::StudentT | Distribution::ChiSquared, false) => &["x", "degrees of freedom"],
+            (Distribution::StudentT | Distribution::ChiSquared, true) => &["probability", "degrees of freedom"],
+            (Distribution::F, false) => &["x", "numerator degrees of freedom", "denominator degrees of freedom"],
+            (Distribution::F, true) => {
+                &["probability", "numerator degrees of freedom", "denominator degrees of freedom"]
+            }
+            (Distribution::Binomial, false) => &["k", "trials", "success probability"],
+            (Distribution::Binomial, true) => &["probability", "trials", "success probability"],
+            (Distribution::Poisson, false) => &["k", "rate"],
+            (Distribution::Exponential, false) => &["x", "rate"],
+            (Distribution::Poisson | Distribution::Exponential, true) => &["probability", "rate"],
+            (Distribution::Uniform, false) => &["x", "lower bound", "upper bound"],
+            (Distribution::Uniform, true) => &["probability", "lower bound", "upper bound"],
+        }
+    }
+
+    fn validate(&self, args: &[f64]) -> Result<(), CalcError> {
+        let name = self.name();
+        self.distribution
+            .check(&args[1..])
+            .map_err(|domain| CalcError::Domain(format!("'{}' needs {}", name, domain)))?;
+        if self.kind == Kind::Quantile && !self.distribution.quantile_defined(args[0]) {
+            return Err(CalcError::Domain(format!("'{}' has no finite result at probability {}", name, args[0])));
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self, _calculator: &Calculator, args: &[f64], _unit: AngleUnit) -> Result<f64, CalcError> {
+        let (x, params) = (args[0], &args[1..]);
+        match self.kind {
+            Kind::Density => Ok(self.distribution.density(x, params)),
+            Kind::Cumulative => self.distribution.cumulative(x, params),
+            Kind::Quantile => self.distribution.quantile(x, params),
+        }
+    }
+
+    fn domain(&self) -> Option<&str> {
+        Some(self.distribution.parameter_domain())
+    }
+
+    fn example(&self) -> Vec<f64> {
+        let x = match (self.distribution, self.kind) {
+            (_, Kind::Quantile) => 0.975,
+            (Distribution::Normal, _) => 1.96,
+            (Distribution::StudentT, _) => 2.228,
+            (Distribution::ChiSquared, _) => 3.841,
+            (Distribution::F, _) => 4.103,
+            (Distribution::Binomial | Distribution::Poisson, _) => 3.0,
+            (Distribution::Exponential | Distribution::Uniform, _) => 0.5,
+        };
+        let params: &[f64] = match self.distribution {
+            Distribution::Normal => &[0.0, 1.0],
+            Distribution::StudentT => &[10.0],
+            Distribution::ChiSquared => &[1.0],
+            Distribution::F => &[2.0, 10.0],
+            Distribution::Binomial => &[10.0, 0.5],
+            Distribution::Poisson => &[2.0],
+            Distribution::Exponential => &[1.0],
+            Distribution::Uniform => &[0.0, 1.0],
+        };
+        std::iter::once(x).chain(params.iter().copied()).collect()
+    }
+}
+
+impl fmt::Display for DistributionOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for DistributionOperation {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DistributionOperation::all()
+            .find(|op| op.name() == s)
+            .ok_or_else(|| CalcError::UnknownOperation(s.to_string()))
+    }
+}
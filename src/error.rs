@@ -0,0 +1,114 @@
+use std::fmt;
+
+/// Every way a calculation can fail. `code()` is part of the HTTP contract,
+/// so existing codes must never be renamed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    MissingOperand(&'static str),
+    InvalidOperand(&'static str),
+    OperandTooLarge { field: &'static str, max_digits: usize },
+    UnknownOperation(String),
+    InvalidRequest(String),
+    BatchTooLarge { size: usize, max: usize },
+    DivisionByZero,
+    Domain(String),
+    Overflow(String),
+    Parse { message: String, position: usize },
+    UnknownFunction(String),
+    UnknownVariable(String),
+    ArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    Registration(String),
+    ReservedName(String),
+    Recursion(String),
+}
+
+impl CalcError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CalcError::MissingOperand(_) => "missing_operand",
+            CalcError::InvalidOperand(_) => "invalid_operand",
+            CalcError::OperandTooLarge { .. } => "operand_too_large",
+            CalcError::UnknownOperation(_) => "unknown_operation",
+            CalcError::InvalidRequest(_) => "invalid_request",
+            CalcError::BatchTooLarge { .. } => "batch_too_large",
+            CalcError::DivisionByZero => "division_by_zero",
+            CalcError::Domain(_) => "domain_error",
+            CalcError::Overflow(_) => "overflow",
+            CalcError::Parse { .. } => "parse_error",
+            CalcError::UnknownFunction(_) => "unknown_function",
+            CalcError::UnknownVariable(_) => "unknown_variable",
+            CalcError::ArgumentCount { .. } => "argument_count",
+            CalcError::Registration(_) => "registration_error",
+            CalcError::ReservedName(_) => "reserved_name",
+            CalcError::Recursion(_) => "recursion_error",
+        }
+    }
+
+    /// The request field the error belongs to, when the error itself knows it.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            CalcError::MissingOperand(field)
+            | CalcError::InvalidOperand(field)
+            | CalcError::OperandTooLarge { field, .. } => Some(field),
+            CalcError::UnknownOperation(_) => Some("operation"),
+            CalcError::Parse { .. } => Some("expression"),
+            _ => None,
+        }
+    }
+
+    /// Errors caused by a malformed request rather than by the maths.
+    pub fn is_request_error(&self) -> bool {
+        matches!(
+            self,
+            CalcError::MissingOperand(_)
+                | CalcError::InvalidOperand(_)
+                | CalcError::OperandTooLarge { .. }
+                | CalcError::UnknownOperation(_)
+                | CalcError::InvalidRequest(_)
+                | CalcError::BatchTooLarge { .. }
+                | CalcError::Parse { .. }
+                | CalcError::ReservedName(_)
+        )
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::MissingOperand(field) => write!(f, "Missing operand '{}'", field),
+            CalcError::InvalidOperand(field) => write!(f, "Operand '{}' is not a valid number", field),
+            CalcError::OperandTooLarge { field, max_digits } => {
+                write!(f, "Operand '{}' has more than {} digits", field, max_digits)
+            }
+            CalcError::UnknownOperation(op) => write!(f, "Unknown operation '{}'", op),
+            CalcError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            CalcError::BatchTooLarge { size, max } => {
+                write!(f, "Batch of {} requests exceeds the maximum of {}", size, max)
+            }
+            CalcError::DivisionByZero => write!(f, "Division by zero is not allowed"),
+            CalcError::Domain(message)
+            | CalcError::Overflow(message)
+            | CalcError::Registration(message)
+            | CalcError::Recursion(message) => {
+                write!(f, "{}", message)
+            }
+            CalcError::Parse { message, position } => {
+                write!(f, "{} at position {}", message, position)
+            }
+            CalcError::UnknownFunction(name) => write!(f, "Unknown function '{}'", name),
+            CalcError::UnknownVariable(name) => write!(f, "Unknown variable '{}'", name),
+            CalcError::ReservedName(name) => write!(f, "'{}' is reserved and cannot be assigned", name),
+            CalcError::ArgumentCount { function, expected, found } => write!(
+                f,
+                "Function '{}' expects {} argument(s) but got {}",
+                function, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
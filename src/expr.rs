@@ -0,0 +1,625 @@
+use crate::angle::AngleUnit;
+use crate::error::CalcError;
+use crate::programmer::parse_integer;
+use crate::Calculator;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expr>),
+    Factorial(Box<Expr>),
+    DoubleFactorial(Box<Expr>),
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Bang,
+    DoubleBang,
+    LParen,
+    RParen,
+    Comma,
+    Equals,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(n) => n.to_string(),
+            Token::Ident(name) => name.clone(),
+            Token::Plus => "+".to_string(),
+            Token::Minus => "-".to_string(),
+            Token::Star => "*".to_string(),
+            Token::Slash => "/".to_string(),
+            Token::Caret => "^".to_string(),
+            Token::Bang => "!".to_string(),
+            Token::DoubleBang => "!!".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+            Token::Comma => ",".to_string(),
+            Token::Equals => "=".to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, CalcError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // Integer literals in other bases: 0xFF, 0o17, 0b1010
+        let radix = match chars.get(i + 1) {
+            Some('x' | 'X') => 16,
+            Some('o' | 'O') => 8,
+            Some('b' | 'B') => 2,
+            _ => 0,
+        };
+        if c == '0' && radix != 0 && chars.get(i + 2).is_some_and(|d| d.is_digit(radix)) {
+            i += 2;
+            while i < chars.len() && (chars[i].is_digit(radix) || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = parse_integer(&text).ok_or_else(|| parse_error(format!("Invalid number '{}'", text), start))?;
+            tokens.push((Token::Number(number as f64), start));
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Scientific notation such as 1.5e-3
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    while j < chars.len() && chars[j].is_ascii_digit() {
+                        j += 1;
+                    }
+                    i = j;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text
+                .parse::<f64>()
+                .map_err(|_| parse_error(format!("Invalid number '{}'", text), start))?;
+            tokens.push((Token::Number(number), start));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            tokens.push((Token::Ident(name), start));
+            continue;
+        }
+
+        // `7!!` is the double factorial, as in the operation's own format;
+        // write `(7!)!` for a factorial of a factorial
+        if c == '!' && chars.get(i + 1) == Some(&'!') {
+            tokens.push((Token::DoubleBang, start));
+            i += 2;
+            continue;
+        }
+
+        let token = match c {
+            '+' => Token::Plus,
+            '-' | '−' => Token::Minus,
+            '*' | '×' => Token::Star,
+            '/' | '÷' => Token::Slash,
+            '^' => Token::Caret,
+            '!' => Token::Bang,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            'π' => Token::Ident("pi".to_string()),
+            _ => return Err(parse_error(format!("Unexpected character '{}'", c), start)),
+        };
+        tokens.push((token, start));
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// Whether `name` can be written in an expression as a variable or function.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn parse_error(message: String, position: usize) -> CalcError {
+    CalcError::Parse { message, position }
+}
+
+/// Deepest an expression may nest. Parentheses, signs, `^` and every
+/// operator in a chain such as `1 + 1 + …` go a level deeper, since parsing
+/// and evaluating thousands of levels would overflow the stack.
+const MAX_NESTING_DEPTH: usize = 256;
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>, input: &str) -> Self {
+        Parser { tokens, pos: 0, end: input.chars().count(), depth: 0 }
+    }
+
+    /// Goes a level deeper, failing once the nesting limit is passed.
+    fn deepen(&mut self) -> Result<(), CalcError> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(parse_error("Expression is nested too deeply".to_string(), self.position()));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Runs `parse` a level deeper.
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<Expr, CalcError>) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        self.deepen()?;
+        let result = parse(self);
+        self.depth = depth;
+        result
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map(|(_, at)| *at).unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CalcError> {
+        let at = self.position();
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(parse_error(
+                format!("Expected '{}' but found '{}'", expected.describe(), token.describe()),
+                at,
+            )),
+            None => Err(parse_error(format!("Expected '{}'", expected.describe()), at)),
+        }
+    }
+
+    /// If the tokens start with `name(a, b, ...) =`, consumes that header and
+    /// returns the name and parameters.
+    fn function_header(&mut self) -> Result<Option<(String, Vec<String>)>, CalcError> {
+        let Some((Token::Ident(name), _)) = self.tokens.first() else {
+            return Ok(None);
+        };
+        if !matches!(self.tokens.get(1), Some((Token::LParen, _))) {
+            return Ok(None);
+        }
+
+        let mut params: Vec<String> = Vec::new();
+        let mut index = 2;
+        loop {
+            match self.tokens.get(index) {
+                Some((Token::RParen, _)) if params.is_empty() => break,
+                Some((Token::Ident(param), at)) => {
+                    if params.contains(param) {
+                        return Err(parse_error(format!("Duplicate parameter '{}'", param), *at));
+                    }
+                    params.push(param.clone());
+                }
+                _ => return Ok(None),
+            }
+            match self.tokens.get(index + 1) {
+                Some((Token::Comma, _)) => index += 2,
+                Some((Token::RParen, _)) => {
+                    index += 1;
+                    break;
+                }
+                _ => return Ok(None),
+            }
+        }
+        if !matches!(self.tokens.get(index + 1), Some((Token::Equals, _))) {
+            return Ok(None);
+        }
+
+        let name = name.clone();
+        self.pos = index + 2;
+        Ok(Some((name, params)))
+    }
+
+    /// Parses what follows the `=` of an assignment or definition.
+    fn definition_body(&mut self) -> Result<Expr, CalcError> {
+        if self.peek().is_none() {
+            return Err(parse_error("Expected an expression after '='".to_string(), self.position()));
+        }
+        self.complete_expression()
+    }
+
+    /// Parses the remaining tokens as one expression, rejecting leftovers.
+    fn complete_expression(&mut self) -> Result<Expr, CalcError> {
+        if self.tokens.is_empty() {
+            return Err(parse_error("Expression is empty".to_string(), 0));
+        }
+        let expr = self.expression()?;
+        if let Some(token) = self.peek() {
+            return Err(parse_error(format!("Unexpected '{}'", token.describe()), self.position()));
+        }
+        Ok(expr)
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Subtract,
+                _ => break,
+            };
+            self.deepen()?;
+            self.next();
+            let rhs = self.term()?;
+            lhs = Expr::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinaryOp::Multiply,
+                Some(Token::Slash) => BinaryOp::Divide,
+                _ => break,
+            };
+            self.deepen()?;
+            self.next();
+            let rhs = self.unary()?;
+            lhs = Expr::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+        }
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    // unary := ('-' | '+') unary | power
+    // Unary minus binds looser than '^', so -2^2 is -(2^2).
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        self.nested(|parser| match parser.peek() {
+            Some(Token::Minus) => {
+                parser.next();
+                Ok(Expr::Negate(Box::new(parser.unary()?)))
+            }
+            Some(Token::Plus) => {
+                parser.next();
+                parser.unary()
+            }
+            _ => parser.power(),
+        })
+    }
+
+    // power := postfix ('^' unary)?
+    // Right-associative: 2^3^2 is 2^(3^2).
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        self.nested(|parser| {
+            let base = parser.postfix()?;
+            if let Some(Token::Caret) = parser.peek() {
+                parser.next();
+                let exponent = parser.unary()?;
+                return Ok(Expr::Binary {
+                    op: BinaryOp::Power,
+                    lhs: Box::new(base),
+                    rhs: Box::new(exponent),
+                });
+            }
+            Ok(base)
+        })
+    }
+
+    // postfix := primary ('!' | '!!')*
+    fn postfix(&mut self) -> Result<Expr, CalcError> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        while let Some(token @ (Token::Bang | Token::DoubleBang)) = self.peek() {
+            let double = *token == Token::DoubleBang;
+            self.deepen()?;
+            self.next();
+            expr = if double {
+                Expr::DoubleFactorial(Box::new(expr))
+            } else {
+                Expr::Factorial(Box::new(expr))
+            };
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    // primary := number | ident | ident '(' arguments ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        self.nested(Parser::atom)
+    }
+
+    fn atom(&mut self) -> Result<Expr, CalcError> {
+        let at = self.position();
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) => {
+                if let Some(Token::LParen) = self.peek() {
+                    self.next();
+                    let mut args = Vec::new();
+                    if let Some(Token::RParen) = self.peek() {
+                        self.next();
+                    } else {
+                        loop {
+                            args.push(self.expression()?);
+                            if let Some(Token::Comma) = self.peek() {
+                                self.next();
+                            } else {
+                                break;
+                            }
+                        }
+                        self.expect(Token::RParen)?;
+                    }
+                    Ok(Expr::Call { name, args })
+                } else {
+                    Ok(Expr::Variable(name))
+                }
+            }
+            Some(Token::LParen) => {
+                let expr = self.expression()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(token) => Err(parse_error(format!("Unexpected '{}'", token.describe()), at)),
+            None => Err(parse_error("Unexpected end of expression".to_string(), at)),
+        }
+    }
+}
+
+/// Parses an infix expression such as `2*(3+sin(30))^2 - ln(5)` into an [`Expr`].
+pub fn parse(input: &str) -> Result<Expr, CalcError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser::new(tokens, input);
+    parser.complete_expression()
+}
+
+/// Deepest chain of user-defined function calls an evaluation may make.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// A line of input: an expression, an assignment such as `r = 2.5` or a
+/// function definition such as `f(x, y) = x^2 + 3*x*y`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Expression(Expr),
+    Assignment { name: String, value: Expr },
+    Definition { name: String, function: UserFunction },
+}
+
+/// A function defined by the user, evaluated with its parameters bound to
+/// the call's arguments. Other names in the body are looked up at call time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunction {
+    pub params: Vec<String>,
+    pub body: Expr,
+    /// The definition as the user wrote it, for listings.
+    pub source: String,
+}
+
+/// Parses an expression, an assignment `name = expression` or a definition
+/// `name(params) = expression`.
+pub fn parse_statement(input: &str) -> Result<Statement, CalcError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser::new(tokens, input);
+
+    if let [(Token::Ident(name), _), (Token::Equals, _), ..] = parser.tokens.as_slice() {
+        let name = name.clone();
+        parser.pos = 2;
+        let value = parser.definition_body()?;
+        return Ok(Statement::Assignment { name, value });
+    }
+    if let Some((name, params)) = parser.function_header()? {
+        let body_start = parser.position();
+        let body = parser.definition_body()?;
+        let body_source: String = input.chars().skip(body_start).collect();
+        let source = format!("{}({}) = {}", name, params.join(", "), body_source.trim());
+        return Ok(Statement::Definition {
+            name,
+            function: UserFunction { params, body, source },
+        });
+    }
+    parser.complete_expression().map(Statement::Expression)
+}
+
+impl Statement {
+    /// Runs the statement: evaluates expressions, stores assignments and
+    /// definitions. Returns the value, or `None` for a definition. Recording
+    /// the value as `ans` is left to the caller.
+    pub fn execute(&self, calculator: &mut Calculator, unit: AngleUnit) -> Result<Option<f64>, CalcError> {
+        match self {
+            Statement::Expression(expr) => expr.evaluate(calculator, unit).map(Some),
+            Statement::Assignment { name, value } => {
+                let value = value.evaluate(calculator, unit)?;
+                calculator.set_variable(name, value)?;
+                Ok(Some(value))
+            }
+            Statement::Definition { name, function } => {
+                calculator.define_function(name, function.clone())?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// What an evaluation can see: the calculator, and inside a user-defined
+/// function the parameters of the innermost call.
+struct Scope<'a> {
+    calculator: &'a Calculator,
+    unit: AngleUnit,
+    params: &'a [String],
+    args: &'a [f64],
+    depth: usize,
+}
+
+impl Expr {
+    /// Evaluates the tree using the `Calculator` methods. Trigonometric
+    /// functions take their argument in `unit`.
+    pub fn evaluate(&self, calculator: &Calculator, unit: AngleUnit) -> Result<f64, CalcError> {
+        self.eval(&Scope { calculator, unit, params: &[], args: &[], depth: 0 })
+    }
+
+    fn eval(&self, scope: &Scope) -> Result<f64, CalcError> {
+        let calculator = scope.calculator;
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Variable(name) => {
+                if let Some(index) = scope.params.iter().position(|param| param == name) {
+                    return Ok(scope.args[index]);
+                }
+                match name.as_str() {
+                    "pi" => Ok(std::f64::consts::PI),
+                    "e" => Ok(std::f64::consts::E),
+                    _ => calculator
+                        .variable(name)
+                        .ok_or_else(|| CalcError::UnknownVariable(name.clone())),
+                }
+            }
+            Expr::Negate(inner) => Ok(-inner.eval(scope)?),
+            Expr::Factorial(inner) => {
+                let value = inner.eval(scope)?;
+                calculator.factorial(value)
+            }
+            Expr::DoubleFactorial(inner) => {
+                let value = inner.eval(scope)?;
+                calculator.double_factorial(value)
+            }
+            Expr::Binary { op, lhs, rhs } => {
+                let a = lhs.eval(scope)?;
+                let b = rhs.eval(scope)?;
+                match op {
+                    BinaryOp::Add => calculator.add(a, b),
+                    BinaryOp::Subtract => calculator.subtract(a, b),
+                    BinaryOp::Multiply => calculator.multiply(a, b),
+                    BinaryOp::Divide => calculator.divide(a, b),
+                    BinaryOp::Power => calculator.power(a, b),
+                }
+            }
+            Expr::Call { name, args } => {
+                let values = args
+                    .iter()
+                    .map(|arg| arg.eval(scope))
+                    .collect::<Result<Vec<f64>, CalcError>>()?;
+                match calculator.function(name) {
+                    Some(function) => call_user_function(scope, name, function, &values),
+                    None => call_function(calculator, scope.unit, name, &values),
+                }
+            }
+        }
+    }
+
+    /// Names of every function the expression calls, directly.
+    pub fn called_functions(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_calls(&mut names);
+        names
+    }
+
+    fn collect_calls<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) | Expr::Variable(_) => {}
+            Expr::Negate(inner) | Expr::Factorial(inner) | Expr::DoubleFactorial(inner) => inner.collect_calls(names),
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.collect_calls(names);
+                rhs.collect_calls(names);
+            }
+            Expr::Call { name, args } => {
+                names.push(name);
+                for arg in args {
+                    arg.collect_calls(names);
+                }
+            }
+        }
+    }
+}
+
+fn call_user_function(scope: &Scope, name: &str, function: &UserFunction, args: &[f64]) -> Result<f64, CalcError> {
+    if args.len() != function.params.len() {
+        return Err(CalcError::ArgumentCount {
+            function: name.to_string(),
+            expected: function.params.len(),
+            found: args.len(),
+        });
+    }
+    if scope.depth >= MAX_CALL_DEPTH {
+        return Err(CalcError::Recursion(format!(
+            "Function calls nested deeper than {} levels",
+            MAX_CALL_DEPTH
+        )));
+    }
+    function.body.eval(&Scope {
+        calculator: scope.calculator,
+        unit: scope.unit,
+        params: &function.params,
+        args,
+        depth: scope.depth + 1,
+    })
+}
+
+/// Name expressions accept as an alias for `log10`. Nothing else may take it,
+/// since calls to it never reach anything but `log10`.
+pub const LOG10_ALIAS: &str = "log";
+
+/// Calls a registered operation; [`LOG10_ALIAS`] is accepted for `log10`.
+fn call_function(calculator: &Calculator, unit: AngleUnit, name: &str, args: &[f64]) -> Result<f64, CalcError> {
+    let name = if name == LOG10_ALIAS { "log10" } else { name };
+    calculator.call(name, args, unit)
+}
@@ -0,0 +1,352 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+use crate::error::CalcError;
+
+/// Entries a [`History`] keeps unless configured otherwise.
+pub const DEFAULT_CAPACITY: usize = 10;
+
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// Latest year a history query date may name, the last with four digits.
+const MAX_YEAR: u32 = 9999;
+
+/// One recorded calculation or session event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HistoryEntry {
+    /// Milliseconds since the Unix epoch, UTC.
+    pub timestamp: u64,
+    /// The operation that ran, e.g. `sqrt`, `evaluate` or `memory_store`.
+    pub operation: String,
+    pub expression: String,
+    /// `None` for failures and for events that produce no value.
+    pub result: Option<f64>,
+    /// The result as shown when `result` alone does not capture it: exact
+    /// digits, or a complex or polar value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    /// A successful entry stamped with the current time.
+    pub fn success(operation: &str, expression: &str, result: Option<f64>) -> Self {
+        HistoryEntry {
+            timestamp: now(),
+            operation: operation.to_string(),
+            expression: expression.to_string(),
+            result,
+            output: None,
+            success: true,
+            error: None,
+        }
+    }
+
+    /// A failed entry stamped with the current time.
+    pub fn failure(operation: &str, expression: &str, error: &CalcError) -> Self {
+        HistoryEntry {
+            timestamp: now(),
+            operation: operation.to_string(),
+            expression: expression.to_string(),
+            result: None,
+            output: None,
+            success: false,
+            error: Some(error.to_string()),
+        }
+    }
+
+    pub fn with_output(mut self, output: impl Into<String>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// The timestamp as RFC 3339, e.g. `2024-05-01T13:45:00Z`.
+    pub fn time(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.error, &self.output, self.result) {
+            (Some(error), _, _) => write!(f, "{}: {}", self.expression, error),
+            (None, Some(output), _) => write!(f, "{} = {}", self.expression, output),
+            (None, None, Some(result)) => write!(f, "{} = {}", self.expression, result),
+            (None, None, None) => write!(f, "{}", self.expression),
+        }
+    }
+}
+
+/// The most recent entries, oldest first, bounded by a capacity.
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+    capacity: usize,
+    /// Every entry pushed since persistence last took them, whatever the
+    /// capacity; `None` until a persistence layer asks for it.
+    unsaved: Option<Vec<HistoryEntry>>,
+}
+
+impl History {
+    pub fn with_capacity(capacity: usize) -> Self {
+        History {
+            entries: VecDeque::new(),
+            capacity,
+            unsaved: None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the capacity, dropping the oldest entries if there are now too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.truncate();
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if let Some(unsaved) = &mut self.unsaved {
+            unsaved.push(entry.clone());
+        }
+        self.entries.push_back(entry);
+        self.truncate();
+    }
+
+    /// From now on, queues every pushed entry for [`History::take_unsaved`],
+    /// including ones the capacity evicts straight away. Persistence layers
+    /// call this once the saved history has been restored.
+    pub fn track_unsaved(&mut self) {
+        self.unsaved.get_or_insert_with(Vec::new);
+    }
+
+    /// Entries pushed since the last call, oldest first. Empty unless
+    /// [`History::track_unsaved`] was called.
+    pub fn take_unsaved(&mut self) -> Vec<HistoryEntry> {
+        self.unsaved.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    /// Entries matching every criterion of `query`, oldest first.
+    pub fn search(&self, query: &HistoryQuery) -> Vec<&HistoryEntry> {
+        self.entries.iter().filter(|entry| query.matches(entry)).collect()
+    }
+
+    fn truncate(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+/// Search criteria for [`History::search`]. Empty criteria match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryQuery {
+    /// Case-insensitive text to find in the expression, operation, output or error.
+    pub text: Option<String>,
+    /// Earliest timestamp, inclusive.
+    pub from: Option<u64>,
+    /// Latest timestamp, inclusive.
+    pub to: Option<u64>,
+}
+
+impl HistoryQuery {
+    /// Builds a query from text and `YYYY-MM-DD` dates, as frontends receive
+    /// them. Both dates are inclusive of the whole day, in UTC.
+    pub fn parse(text: Option<&str>, from: Option<&str>, to: Option<&str>) -> Result<Self, CalcError> {
+        Ok(HistoryQuery {
+            text: text.map(str::trim).filter(|text| !text.is_empty()).map(str::to_lowercase),
+            from: from.map(parse_date).transpose()?,
+            to: to.map(|date| parse_date(date).map(|start| start + MILLIS_PER_DAY - 1)).transpose()?,
+        })
+    }
+
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        if self.from.is_some_and(|from| entry.timestamp < from) || self.to.is_some_and(|to| entry.timestamp > to) {
+            return false;
+        }
+        let Some(text) = &self.text else {
+            return true;
+        };
+        [Some(&entry.expression), Some(&entry.operation), entry.output.as_ref(), entry.error.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|field| field.to_lowercase().contains(text.as_str()))
+    }
+}
+
+/// File formats history can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(CalcError::InvalidRequest(format!("Unknown export format '{}'; use json or csv", s))),
+        }
+    }
+}
+
+/// Renders entries as a JSON array or as CSV with a header row. CSV
+/// timestamps are RFC 3339 so spreadsheets can read them.
+pub fn export(entries: &[&HistoryEntry], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(entries).unwrap_or_else(|_| "[]".to_string()),
+        ExportFormat::Csv => {
+            let mut csv = String::from("timestamp,operation,expression,result,output,success,error\n");
+            for entry in entries {
+                let fields = [
+                    entry.time(),
+                    entry.operation.clone(),
+                    entry.expression.clone(),
+                    entry.result.map(|result| result.to_string()).unwrap_or_default(),
+                    entry.output.clone().unwrap_or_default(),
+                    entry.success.to_string(),
+                    entry.error.clone().unwrap_or_default(),
+                ];
+                let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Midnight UTC at the start of a `YYYY-MM-DD` date, in Unix milliseconds.
+pub fn parse_date(date: &str) -> Result<u64, CalcError> {
+    let invalid = || CalcError::InvalidRequest(format!("'{}' is not a date in YYYY-MM-DD form", date));
+    let mut parts = date.trim().splitn(3, '-');
+    let mut part = || parts.next().and_then(|part| part.parse::<u32>().ok()).ok_or_else(invalid);
+    let (year, month, day) = (part()?, part()?, part()?);
+    if !(1970..=MAX_YEAR).contains(&year) || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+    Ok(days_from_civil(year, month, day) * MILLIS_PER_DAY)
+}
+
+/// Formats Unix milliseconds as RFC 3339 in UTC, to the second.
+pub fn format_timestamp(millis: u64) -> String {
+    let (days, seconds) = (millis / MILLIS_PER_DAY, millis % MILLIS_PER_DAY / 1000);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Leap years from year 1 up to and including `year`.
+fn leap_years_through(year: u32) -> u64 {
+    let year = year as u64;
+    year / 4 - year / 100 + year / 400
+}
+
+/// Days since 1970-01-01 for a date on or after it.
+fn days_from_civil(year: u32, month: u32, day: u32) -> u64 {
+    let mut days = 365 * (year - 1970) as u64 + leap_years_through(year - 1) - leap_years_through(1969);
+    days += (1..month).map(|m| days_in_month(year, m) as u64).sum::<u64>();
+    days + day as u64 - 1
+}
+
+fn civil_from_days(mut days: u64) -> (u32, u32, u32) {
+    let mut year = 1970;
+    loop {
+        let length = if is_leap_year(year) { 366 } else { 365 };
+        if days < length {
+            break;
+        }
+        days -= length;
+        year += 1;
+    }
+    let mut month = 1;
+    while days >= days_in_month(year, month) as u64 {
+        days -= days_in_month(year, month) as u64;
+        month += 1;
+    }
+    (year, month, days as u32 + 1)
+}
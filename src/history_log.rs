@@ -0,0 +1,165 @@
+use calculator::HistoryEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One line of the history file.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Record {
+    Entry {
+        session: String,
+        #[serde(flatten)]
+        entry: HistoryEntry,
+    },
+    Clear {
+        session: String,
+        timestamp: u64,
+    },
+}
+
+/// Where each session's most recent entries start in the file, at most the
+/// history capacity of them and only since the session last cleared.
+type Index = HashMap<String, VecDeque<u64>>;
+
+/// Append-only JSON lines file holding every session's history, so it
+/// survives restarts and past calculations can be audited. Clearing a
+/// session's history appends a marker rather than rewriting the file.
+///
+/// The file is scanned once, on the first load, for an index of where each
+/// session's entries are; later loads read only those lines.
+pub struct HistoryLog {
+    path: PathBuf,
+    capacity: usize,
+    index: Mutex<Option<Index>>,
+}
+
+impl HistoryLog {
+    /// A log whose loads return at most `capacity` entries per session.
+    pub fn new(path: impl Into<PathBuf>, capacity: usize) -> Self {
+        HistoryLog {
+            path: path.into(),
+            capacity,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// The session's last entries, up to the capacity, since it last
+    /// cleared its history, oldest first. A missing file is an empty
+    /// history; unreadable lines are skipped.
+    pub fn load(&self, session: &str) -> io::Result<Vec<HistoryEntry>> {
+        let mut index = self.lock();
+        let index = match &mut *index {
+            Some(index) => index,
+            empty => empty.insert(self.scan()?),
+        };
+        let Some(offsets) = index.get(session) else {
+            return Ok(Vec::new());
+        };
+
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut entries = Vec::new();
+        let mut line = String::new();
+        for &offset in offsets {
+            reader.seek(SeekFrom::Start(offset))?;
+            line.clear();
+            reader.read_line(&mut line)?;
+            if let Ok(Record::Entry { entry, .. }) = serde_json::from_str(&line) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Reads the whole file into an index.
+    fn scan(&self) -> io::Result<Index> {
+        let mut index = Index::new();
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(index),
+            Err(e) => return Err(e),
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut offset = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                return Ok(index);
+            }
+            if let Ok(record) = serde_json::from_str::<Record>(&line) {
+                add_to_index(&mut index, &record, offset, self.capacity);
+            }
+            offset += read as u64;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Index>> {
+        self.index.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn append<'a>(&self, session: &str, entries: impl IntoIterator<Item = &'a HistoryEntry>) -> io::Result<()> {
+        let records = entries.into_iter().map(|entry| Record::Entry {
+            session: session.to_string(),
+            entry: entry.clone(),
+        });
+        self.write(records)
+    }
+
+    pub fn record_clear(&self, session: &str) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        self.write([Record::Clear {
+            session: session.to_string(),
+            timestamp,
+        }])
+    }
+
+    fn write(&self, records: impl IntoIterator<Item = Record>) -> io::Result<()> {
+        let mut lines = String::new();
+        let mut starts = Vec::new();
+        for record in records {
+            let line = serde_json::to_string(&record).map_err(io::Error::other)?;
+            starts.push((record, lines.len() as u64));
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let mut index = self.lock();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let end = file.seek(SeekFrom::End(0))?;
+        file.write_all(lines.as_bytes())?;
+        if let Some(index) = &mut *index {
+            for (record, start) in &starts {
+                add_to_index(index, record, end + start, self.capacity);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn add_to_index(index: &mut Index, record: &Record, offset: u64, capacity: usize) {
+    match record {
+        Record::Entry { session, .. } => {
+            let offsets = index.entry(session.clone()).or_default();
+            offsets.push_back(offset);
+            if offsets.len() > capacity {
+                offsets.pop_front();
+            }
+        }
+        Record::Clear { session, .. } => {
+            index.remove(session);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! The calculator engine behind the `hello-ngrok` web server and REPL.
+//!
+//! [`Calculator`] holds per-user state (memory, variables, [`History`] and
+//! angle unit) and exposes every operation as a method returning
+//! `Result<_, CalcError>`.
+//! [`Operation`] names the built-in operations; together with any custom
+//! [`Operator`]s they make up the [`Registry`] that frontends dispatch
+//! through; [`Statistic`]s work on whole data sets instead, and
+//! [`analysis`] fits curves to them. [`number_theory`] works on exact
+//! 64-bit integers. [`expr::parse`] turns a free-form
+//! expression into a tree that evaluates against a `Calculator`. The engine does no I/O of its own.
+
+pub mod analysis;
+pub mod angle;
+pub mod bigint;
+mod calculator;
+pub mod complex;
+pub mod distribution;
+pub mod error;
+pub mod expr;
+pub mod history;
+pub mod memory;
+pub mod number_theory;
+mod operation;
+pub mod programmer;
+pub mod registry;
+pub mod special;
+pub mod statistics;
+
+pub use angle::AngleUnit;
+pub use bigint::BigInt;
+pub use calculator::Calculator;
+pub use complex::Complex;
+pub use error::CalcError;
+pub use history::{History, HistoryEntry};
+pub use memory::{Memory, Register, RegisterNumber};
+pub use number_theory::NumberOperation;
+pub use operation::Operation;
+pub use programmer::{BitOperation, WordSize};
+pub use registry::{Function, Operator, Registry};
+pub use statistics::Statistic;
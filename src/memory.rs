@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::openapi::{schema::Type, ObjectBuilder, RefOr, Schema};
+use utoipa::{PartialSchema, ToSchema};
+
+/// Number of numbered registers, `M1` to `M9`.
+pub const NUMBERED_REGISTERS: usize = 9;
+
+/// A memory register: the main `M` that MS, MR, M+, M- and MC act on by
+/// default, or one of the numbered registers `M1` to `M9`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Register {
+    #[default]
+    Main,
+    /// `M1` to `M9`, built with [`Register::numbered`] or by parsing a name.
+    Numbered(RegisterNumber),
+}
+
+/// The number of a numbered register, always 1 to [`NUMBERED_REGISTERS`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegisterNumber(u8);
+
+impl RegisterNumber {
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl Register {
+    /// `M1` to `M9`, or `None` for any other number.
+    pub fn numbered(n: u8) -> Option<Register> {
+        (1..=NUMBERED_REGISTERS as u8).contains(&n).then_some(Register::Numbered(RegisterNumber(n)))
+    }
+
+    /// `M`, then `M1` to `M9`.
+    pub fn all() -> impl Iterator<Item = Register> {
+        std::iter::once(Register::Main).chain((1..=NUMBERED_REGISTERS as u8).filter_map(Register::numbered))
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Register::Main => 0,
+            Register::Numbered(n) => n.get() as usize,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::Main => write!(f, "M"),
+            Register::Numbered(n) => write!(f, "M{}", n.get()),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        let number = name.strip_prefix(['M', 'm']).unwrap_or(name);
+        if number.is_empty() {
+            return Ok(Register::Main);
+        }
+        number
+            .parse::<u8>()
+            .ok()
+            .and_then(Register::numbered)
+            .ok_or_else(|| format!("Unknown memory register '{}'; use M or M1 to M{}", s, NUMBERED_REGISTERS))
+    }
+}
+
+/// Registers travel as their names, `M` or `M1` to `M9`.
+impl PartialSchema for Register {
+    fn schema() -> RefOr<Schema> {
+        let names: Vec<String> = Register::all().map(|register| register.to_string()).collect();
+        ObjectBuilder::new().schema_type(Type::String).enum_values(Some(names)).into()
+    }
+}
+
+impl ToSchema for Register {}
+
+impl TryFrom<String> for Register {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<Register> for String {
+    fn from(register: Register) -> Self {
+        register.to_string()
+    }
+}
+
+/// The contents of every register. All start at zero.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Memory {
+    registers: [f64; NUMBERED_REGISTERS + 1],
+}
+
+impl Memory {
+    pub fn get(&self, register: Register) -> f64 {
+        self.registers[register.index()]
+    }
+
+    pub fn set(&mut self, register: Register, value: f64) {
+        self.registers[register.index()] = value;
+    }
+
+    /// Every register with its value, `M` first.
+    pub fn iter(&self) -> impl Iterator<Item = (Register, f64)> + '_ {
+        Register::all().map(|register| (register, self.get(register)))
+    }
+
+    pub fn clear(&mut self) {
+        self.registers = Default::default();
+    }
+}
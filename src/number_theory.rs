@@ -0,0 +1,456 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::angle::AngleUnit;
+use crate::error::CalcError;
+use crate::programmer::integer_from_f64;
+use crate::registry::Operator;
+use crate::Calculator;
+
+/// Prime factors below this are found by trial division; Pollard's rho
+/// splits whatever is left.
+const TRIAL_DIVISION_LIMIT: u64 = 1_000;
+
+/// Witnesses that make Miller-Rabin deterministic for every 64-bit integer.
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Greatest common divisor; `gcd(0, 0)` is 0.
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple, or `None` if it does not fit in 64 bits.
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// `a` modulo `m` rounding the quotient down, so the result takes the sign
+/// of `m`: `-7 mod 3` is 2.
+pub fn mod_floor(a: i128, m: i128) -> Result<i128, CalcError> {
+    if m == 0 {
+        return Err(CalcError::DivisionByZero);
+    }
+    let r = a % m;
+    Ok(if r != 0 && (r < 0) != (m < 0) { r + m } else { r })
+}
+
+/// `a` modulo `m` truncating the quotient toward zero, so the result takes
+/// the sign of `a`: `-7 rem 3` is -1.
+pub fn mod_trunc(a: i128, m: i128) -> Result<i128, CalcError> {
+    if m == 0 {
+        return Err(CalcError::DivisionByZero);
+    }
+    Ok(a % m)
+}
+
+/// `base^exponent mod modulus` by repeated squaring. A negative exponent
+/// raises the modular inverse of `base` instead.
+pub fn mod_pow(base: i128, exponent: i128, modulus: i128) -> Result<u64, CalcError> {
+    let m = positive_modulus(modulus)?;
+    let base = if exponent < 0 {
+        mod_inverse(base, modulus)?
+    } else {
+        mod_floor(base, modulus)? as u64
+    };
+    Ok(pow_mod(base, exponent.unsigned_abs(), m))
+}
+
+/// The `x` in `0..modulus` with `a·x ≡ 1 (mod modulus)`, which exists only
+/// when `a` and `modulus` are coprime.
+pub fn mod_inverse(a: i128, modulus: i128) -> Result<u64, CalcError> {
+    positive_modulus(modulus)?;
+    // Extended Euclid, tracking only the coefficient of `a`
+    let (mut r, mut next_r) = (modulus, mod_floor(a, modulus)?);
+    let (mut t, mut next_t) = (0i128, 1i128);
+    while next_r != 0 {
+        let quotient = r / next_r;
+        (r, next_r) = (next_r, r - quotient * next_r);
+        (t, next_t) = (next_t, t - quotient * next_t);
+    }
+    if r != 1 {
+        return Err(CalcError::Domain(format!("{} has no inverse modulo {}", a, modulus)));
+    }
+    Ok(mod_floor(t, modulus)? as u64)
+}
+
+fn positive_modulus(modulus: i128) -> Result<u64, CalcError> {
+    match modulus {
+        0 => Err(CalcError::DivisionByZero),
+        m if m < 0 => Err(CalcError::Domain("The modulus must be positive".to_string())),
+        m => Ok(m as u64),
+    }
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u128, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller-Rabin test, exact for every `u64`.
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let shift = (n - 1).trailing_zeros();
+    let odd = (n - 1) >> shift;
+    WITNESSES.iter().all(|&witness| {
+        let mut x = pow_mod(witness, odd as u128, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..shift).any(|_| {
+            x = mul_mod(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+/// Prime factors of `n` in ascending order, repeated by multiplicity.
+/// Empty for 0 and 1.
+pub fn factorize(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    if n < 2 {
+        return factors;
+    }
+    let mut p = 2;
+    while p < TRIAL_DIVISION_LIMIT && p * p <= n {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+        p += 1;
+    }
+    let mut composites = vec![n];
+    while let Some(n) = composites.pop() {
+        if n == 1 {
+            continue;
+        }
+        if is_prime(n) {
+            factors.push(n);
+        } else {
+            let divisor = pollard_rho(n);
+            composites.extend([divisor, n / divisor]);
+        }
+    }
+    factors.sort_unstable();
+    factors
+}
+
+/// A non-trivial divisor of an odd composite `n`.
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let step = |x: u64| ((x as u128 * x as u128 + c) % n as u128) as u64;
+        let (mut x, mut y, mut divisor) = (2, 2, 1);
+        while divisor == 1 {
+            x = step(x);
+            y = step(step(y));
+            divisor = gcd(x.abs_diff(y), n);
+        }
+        if divisor != n {
+            return divisor;
+        }
+    }
+    unreachable!("every composite has a divisor")
+}
+
+/// The smallest prime greater than `n`, or `None` past the largest 64-bit prime.
+pub fn next_prime(n: u64) -> Option<u64> {
+    (n.checked_add(1)?.max(2)..=u64::MAX).find(|&candidate| is_prime(candidate))
+}
+
+/// The largest prime less than `n`, or `None` for 2 and below.
+pub fn prev_prime(n: u64) -> Option<u64> {
+    (2..n).rev().find(|&candidate| is_prime(candidate))
+}
+
+/// Euler's totient: how many of `1..=n` are coprime to `n`.
+pub fn totient(n: u64) -> u64 {
+    let mut primes = factorize(n);
+    primes.dedup();
+    primes.into_iter().fold(n, |result, p| result / p * (p - 1))
+}
+
+/// Every positive divisor of `n` in ascending order; empty for 0.
+pub fn divisors(n: u64) -> Vec<u64> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut divisors = vec![1];
+    for power in factorize(n).chunk_by(|a, b| a == b) {
+        let count = divisors.len();
+        let mut multiplier = 1;
+        for &p in power {
+            multiplier *= p;
+            divisors.extend_from_within(..count);
+            let start = divisors.len() - count;
+            for divisor in &mut divisors[start..] {
+                *divisor *= multiplier;
+            }
+        }
+    }
+    divisors.sort_unstable();
+    divisors
+}
+
+/// Number theory on 64-bit integers. Like [`BitOperation`](crate::BitOperation)
+/// they are registered by default, so expressions can call them; the
+/// server additionally evaluates them on exact integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberOperation {
+    Gcd,
+    Lcm,
+    /// Remainder of floored division, with the sign of the modulus.
+    Mod,
+    /// Remainder of truncated division, with the sign of the dividend.
+    Rem,
+    ModPow,
+    ModInverse,
+    IsPrime,
+    Factorize,
+    NextPrime,
+    PrevPrime,
+    Totient,
+    Divisors,
+}
+
+impl NumberOperation {
+    pub const ALL: [NumberOperation; 12] = [
+        NumberOperation::Gcd,
+        NumberOperation::Lcm,
+        NumberOperation::Mod,
+        NumberOperation::Rem,
+        NumberOperation::ModPow,
+        NumberOperation::ModInverse,
+        NumberOperation::IsPrime,
+        NumberOperation::Factorize,
+        NumberOperation::NextPrime,
+        NumberOperation::PrevPrime,
+        NumberOperation::Totient,
+        NumberOperation::Divisors,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NumberOperation::Gcd => "gcd",
+            NumberOperation::Lcm => "lcm",
+            NumberOperation::Mod => "mod",
+            NumberOperation::Rem => "rem",
+            NumberOperation::ModPow => "modpow",
+            NumberOperation::ModInverse => "modinv",
+            NumberOperation::IsPrime => "is_prime",
+            NumberOperation::Factorize => "factorize",
+            NumberOperation::NextPrime => "next_prime",
+            NumberOperation::PrevPrime => "prev_prime",
+            NumberOperation::Totient => "totient",
+            NumberOperation::Divisors => "divisors",
+        }
+    }
+
+    /// Whether the operation results in a list rather than a single integer.
+    pub fn is_list(self) -> bool {
+        matches!(self, NumberOperation::Factorize | NumberOperation::Divisors)
+    }
+
+    /// Applies the operation to integers in the range of `i64` or `u64`.
+    /// Most operations have one result; `factorize` returns the prime
+    /// factors with repeats and `divisors` every divisor, both ascending.
+    /// `is_prime` gives 1 or 0.
+    pub fn apply(self, args: &[i128]) -> Result<Vec<i128>, CalcError> {
+        let a = args.first().copied().unwrap_or_default();
+        let b = args.get(1).copied().unwrap_or_default();
+        let natural = || -> Result<u64, CalcError> {
+            u64::try_from(a)
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| CalcError::Domain(format!("'{}' needs a positive integer", self.name())))
+        };
+
+        let result = match self {
+            NumberOperation::Gcd => args.iter().fold(0, |result, &arg| gcd(result, arg.unsigned_abs() as u64)) as i128,
+            NumberOperation::Lcm => match args.iter().try_fold(1, |result, &arg| lcm(result, arg.unsigned_abs() as u64)) {
+                Some(multiple) => multiple as i128,
+                None => return Err(CalcError::Overflow("The lcm does not fit in 64 bits".to_string())),
+            },
+            NumberOperation::Mod => mod_floor(a, b)?,
+            NumberOperation::Rem => mod_trunc(a, b)?,
+            NumberOperation::ModPow => mod_pow(a, b, args.get(2).copied().unwrap_or_default())? as i128,
+            NumberOperation::ModInverse => mod_inverse(a, b)? as i128,
+            NumberOperation::IsPrime => (a > 0 && is_prime(a as u64)) as i128,
+            NumberOperation::Factorize => {
+                let n = natural()?;
+                return Ok(factorize(n).into_iter().map(i128::from).collect());
+            }
+            NumberOperation::NextPrime => match next_prime(a.max(0) as u64) {
+                Some(prime) => prime as i128,
+                None => return Err(CalcError::Overflow(format!("There is no 64-bit prime above {}", a))),
+            },
+            NumberOperation::PrevPrime => match prev_prime(a.clamp(0, u64::MAX as i128) as u64) {
+                Some(prime) => prime as i128,
+                None => return Err(CalcError::Domain(format!("There is no prime below {}", a))),
+            },
+            NumberOperation::Totient => totient(natural()?) as i128,
+            NumberOperation::Divisors => {
+                let n = natural()?;
+                return Ok(divisors(n).into_iter().map(i128::from).collect());
+            }
+        };
+        Ok(vec![result])
+    }
+
+    /// How the operation is written, given its operands as text.
+    pub fn describe(self, args: &[String]) -> String {
+        let arg = |index: usize| args.get(index).map(String::as_str).unwrap_or_default();
+        match self {
+            NumberOperation::Mod => format!("{} mod {}", arg(0), arg(1)),
+            NumberOperation::Rem => format!("{} rem {}", arg(0), arg(1)),
+            NumberOperation::ModPow => format!("{}^{} mod {}", arg(0), arg(1), arg(2)),
+            NumberOperation::ModInverse => format!("{}⁻¹ mod {}", arg(0), arg(1)),
+            NumberOperation::Totient => format!("φ({})", arg(0)),
+            _ => format!("{}({})", self.name(), args.join(", ")),
+        }
+    }
+
+    /// How the results of [`NumberOperation::apply`] are shown, e.g.
+    /// `2^3 × 3^2 × 5` for a factorization or `true` for a prime.
+    pub fn describe_results(self, results: &[i128]) -> String {
+        match self {
+            NumberOperation::IsPrime => (results.first() == Some(&1)).to_string(),
+            NumberOperation::Factorize if results.is_empty() => "1".to_string(),
+            NumberOperation::Factorize => results
+                .chunk_by(|a, b| a == b)
+                .map(|power| match power.len() {
+                    1 => power[0].to_string(),
+                    exponent => format!("{}^{}", power[0], exponent),
+                })
+                .collect::<Vec<_>>()
+                .join(" × "),
+            NumberOperation::Divisors => {
+                let results: Vec<String> = results.iter().map(i128::to_string).collect();
+                format!("[{}]", results.join(", "))
+            }
+            _ => results.first().map(i128::to_string).unwrap_or_default(),
+        }
+    }
+}
+
+impl Operator for NumberOperation {
+    fn name(&self) -> &str {
+        NumberOperation::name(*self)
+    }
+
+    fn operands(&self) -> &[&'static str] {
+        match self {
+            NumberOperation::Gcd | NumberOperation::Lcm => &["a", "b"],
+            NumberOperation::Mod | NumberOperation::Rem | NumberOperation::ModInverse => &["a", "modulus"],
+            NumberOperation::ModPow => &["base", "exponent", "modulus"],
+            _ => &["n"],
+        }
+    }
+
+    fn variadic(&self) -> bool {
+        matches!(self, NumberOperation::Gcd | NumberOperation::Lcm)
+    }
+
+    /// Returns the single result; `factorize` and `divisors` give their
+    /// first, the smallest prime factor and 1.
+    fn evaluate(&self, _calculator: &Calculator, args: &[f64], _unit: AngleUnit) -> Result<f64, CalcError> {
+        let args = args
+            .iter()
+            .map(|&arg| integer_from_f64(arg))
+            .collect::<Option<Vec<i128>>>()
+            .ok_or_else(|| CalcError::Domain(format!("'{}' needs integer operands", self.name())))?;
+        Ok(self.apply(&args)?.first().copied().unwrap_or_default() as f64)
+    }
+
+    fn format(&self, args: &[f64], _unit: AngleUnit) -> String {
+        let args: Vec<String> = args.iter().map(f64::to_string).collect();
+        self.describe(&args)
+    }
+
+    fn symbol(&self) -> &str {
+        match self {
+            NumberOperation::ModInverse => "⁻¹ mod",
+            NumberOperation::Totient => "φ",
+            _ => self.name(),
+        }
+    }
+
+    fn domain(&self) -> Option<&str> {
+        let domain = match self {
+            NumberOperation::Gcd | NumberOperation::Lcm | NumberOperation::IsPrime => "64-bit integers",
+            NumberOperation::Mod | NumberOperation::Rem => "64-bit integers; modulus ≠ 0",
+            NumberOperation::ModPow => "64-bit integers; modulus > 0",
+            NumberOperation::ModInverse => "64-bit integers; a coprime to modulus > 0",
+            NumberOperation::NextPrime => "64-bit integers below the largest 64-bit prime",
+            NumberOperation::PrevPrime => "64-bit integers > 2",
+            NumberOperation::Factorize | NumberOperation::Totient | NumberOperation::Divisors => {
+                "positive 64-bit integers"
+            }
+        };
+        Some(domain)
+    }
+
+    fn example(&self) -> Vec<f64> {
+        match self {
+            NumberOperation::Gcd => vec![12.0, 18.0],
+            NumberOperation::Lcm => vec![4.0, 6.0],
+            NumberOperation::Mod | NumberOperation::Rem => vec![-7.0, 3.0],
+            NumberOperation::ModPow => vec![4.0, 13.0, 497.0],
+            NumberOperation::ModInverse => vec![3.0, 11.0],
+            NumberOperation::IsPrime => vec![97.0],
+            NumberOperation::Factorize => vec![360.0],
+            NumberOperation::NextPrime | NumberOperation::PrevPrime => vec![100.0],
+            NumberOperation::Totient => vec![36.0],
+            NumberOperation::Divisors => vec![28.0],
+        }
+    }
+
+    fn error_operand(&self) -> Option<usize> {
+        match self {
+            NumberOperation::Gcd | NumberOperation::Lcm => None,
+            NumberOperation::Mod | NumberOperation::Rem | NumberOperation::ModInverse => Some(1),
+            NumberOperation::ModPow => Some(2),
+            _ => Some(0),
+        }
+    }
+}
+
+impl fmt::Display for NumberOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for NumberOperation {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NumberOperation::ALL
+            .into_iter()
+            .find(|op| op.name() == s)
+            .ok_or_else(|| CalcError::UnknownOperation(s.to_string()))
+    }
+}
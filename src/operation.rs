@@ -0,0 +1,390 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::angle::AngleUnit;
+use crate::error::CalcError;
+use crate::registry::Operator;
+use crate::Calculator;
+
+/// An operation a frontend can request by name, e.g. the `operation` field
+/// of `/api/calculate`. Names are snake case and part of the HTTP contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Sqrt,
+    Sin,
+    Cos,
+    Tan,
+    Sec,
+    Csc,
+    Cot,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sinh,
+    Cosh,
+    Tanh,
+    Asinh,
+    Acosh,
+    Atanh,
+    Ln,
+    Log10,
+    DegreesToRadians,
+    RadiansToDegrees,
+    Square,
+    Reciprocal,
+    Factorial,
+    Gamma,
+    LnGamma,
+    Beta,
+    DoubleFactorial,
+    Combinations,
+    Permutations,
+    Multinomial,
+    Catalan,
+    StirlingFirst,
+    StirlingSecond,
+    Pi,
+    E,
+    Abs,
+    ToPolar,
+    FromPolar,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 44] = [
+        Operation::Add,
+        Operation::Subtract,
+        Operation::Multiply,
+        Operation::Divide,
+        Operation::Power,
+        Operation::Sqrt,
+        Operation::Sin,
+        Operation::Cos,
+        Operation::Tan,
+        Operation::Sec,
+        Operation::Csc,
+        Operation::Cot,
+        Operation::Asin,
+        Operation::Acos,
+        Operation::Atan,
+        Operation::Atan2,
+        Operation::Sinh,
+        Operation::Cosh,
+        Operation::Tanh,
+        Operation::Asinh,
+        Operation::Acosh,
+        Operation::Atanh,
+        Operation::Ln,
+        Operation::Log10,
+        Operation::DegreesToRadians,
+        Operation::RadiansToDegrees,
+        Operation::Square,
+        Operation::Reciprocal,
+        Operation::Factorial,
+        Operation::Gamma,
+        Operation::LnGamma,
+        Operation::Beta,
+        Operation::DoubleFactorial,
+        Operation::Combinations,
+        Operation::Permutations,
+        Operation::Multinomial,
+        Operation::Catalan,
+        Operation::StirlingFirst,
+        Operation::StirlingSecond,
+        Operation::Pi,
+        Operation::E,
+        Operation::Abs,
+        Operation::ToPolar,
+        Operation::FromPolar,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Add => "add",
+            Operation::Subtract => "subtract",
+            Operation::Multiply => "multiply",
+            Operation::Divide => "divide",
+            Operation::Power => "power",
+            Operation::Sqrt => "sqrt",
+            Operation::Sin => "sin",
+            Operation::Cos => "cos",
+            Operation::Tan => "tan",
+            Operation::Sec => "sec",
+            Operation::Csc => "csc",
+            Operation::Cot => "cot",
+            Operation::Asin => "asin",
+            Operation::Acos => "acos",
+            Operation::Atan => "atan",
+            Operation::Atan2 => "atan2",
+            Operation::Sinh => "sinh",
+            Operation::Cosh => "cosh",
+            Operation::Tanh => "tanh",
+            Operation::Asinh => "asinh",
+            Operation::Acosh => "acosh",
+            Operation::Atanh => "atanh",
+            Operation::Ln => "ln",
+            Operation::Log10 => "log10",
+            Operation::DegreesToRadians => "degrees_to_radians",
+            Operation::RadiansToDegrees => "radians_to_degrees",
+            Operation::Square => "square",
+            Operation::Reciprocal => "reciprocal",
+            Operation::Factorial => "factorial",
+            Operation::Gamma => "gamma",
+            Operation::LnGamma => "lgamma",
+            Operation::Beta => "beta",
+            Operation::DoubleFactorial => "double_factorial",
+            Operation::Combinations => "ncr",
+            Operation::Permutations => "npr",
+            Operation::Multinomial => "multinomial",
+            Operation::Catalan => "catalan",
+            Operation::StirlingFirst => "stirling1",
+            Operation::StirlingSecond => "stirling2",
+            Operation::Pi => "pi",
+            Operation::E => "e",
+            Operation::Abs => "abs",
+            Operation::ToPolar => "to_polar",
+            Operation::FromPolar => "from_polar",
+        }
+    }
+}
+
+impl Operator for Operation {
+    fn name(&self) -> &str {
+        Operation::name(*self)
+    }
+
+    fn operands(&self) -> &[&'static str] {
+        match self {
+            Operation::Add | Operation::Subtract | Operation::Multiply => &["first number", "second number"],
+            Operation::Divide => &["dividend", "divisor"],
+            Operation::Power => &["base", "exponent"],
+            Operation::Atan2 => &["y", "x"],
+            Operation::FromPolar => &["magnitude", "angle"],
+            Operation::Sin | Operation::Cos | Operation::Tan | Operation::Sec | Operation::Csc | Operation::Cot => {
+                &["angle"]
+            }
+            Operation::DegreesToRadians => &["degrees"],
+            Operation::RadiansToDegrees => &["radians"],
+            Operation::Factorial | Operation::DoubleFactorial | Operation::Catalan => &["n"],
+            Operation::Beta => &["a", "b"],
+            Operation::Combinations | Operation::Permutations => &["n", "r"],
+            Operation::Multinomial => &["k1", "k2"],
+            Operation::StirlingFirst | Operation::StirlingSecond => &["n", "k"],
+            Operation::Pi | Operation::E => &[],
+            _ => &["value"],
+        }
+    }
+
+    fn variadic(&self) -> bool {
+        *self == Operation::Multinomial
+    }
+
+    /// Real-mode evaluation. `to_polar` yields the magnitude and `from_polar`
+    /// the real part; the server's complex path returns the other half.
+    fn evaluate(&self, calculator: &Calculator, args: &[f64], unit: AngleUnit) -> Result<f64, CalcError> {
+        let inverse = |radians: f64| unit.radians_to_unit(radians);
+        let x = args.first().copied().unwrap_or_default();
+        let y = args.get(1).copied().unwrap_or_default();
+        match self {
+            Operation::Add => calculator.add(x, y),
+            Operation::Subtract => calculator.subtract(x, y),
+            Operation::Multiply => calculator.multiply(x, y),
+            Operation::Divide => calculator.divide(x, y),
+            Operation::Power => calculator.power(x, y),
+            Operation::Sqrt => calculator.sqrt(x),
+            Operation::Sin => Ok(calculator.sin(x, unit)),
+            Operation::Cos => Ok(calculator.cos(x, unit)),
+            Operation::Tan => calculator.tan(x, unit),
+            Operation::Sec => calculator.sec(x, unit),
+            Operation::Csc => calculator.csc(x, unit),
+            Operation::Cot => calculator.cot(x, unit),
+            Operation::Asin => calculator.asin(x).map(inverse),
+            Operation::Acos => calculator.acos(x).map(inverse),
+            Operation::Atan => Ok(inverse(calculator.atan(x))),
+            Operation::Atan2 => calculator.atan2(x, y).map(inverse),
+            Operation::Sinh => calculator.sinh(x),
+            Operation::Cosh => calculator.cosh(x),
+            Operation::Tanh => Ok(calculator.tanh(x)),
+            Operation::Asinh => Ok(calculator.asinh(x)),
+            Operation::Acosh => calculator.acosh(x),
+            Operation::Atanh => calculator.atanh(x),
+            Operation::Ln => calculator.ln(x),
+            Operation::Log10 => calculator.log10(x),
+            Operation::DegreesToRadians => Ok(calculator.degrees_to_radians(x)),
+            Operation::RadiansToDegrees => Ok(calculator.radians_to_degrees(x)),
+            Operation::Square => calculator.multiply(x, x),
+            Operation::Reciprocal => calculator.divide(1.0, x),
+            Operation::Factorial => calculator.factorial(x),
+            Operation::Gamma => calculator.gamma(x),
+            Operation::LnGamma => calculator.ln_gamma(x),
+            Operation::Beta => calculator.beta(x, y),
+            Operation::DoubleFactorial => calculator.double_factorial(x),
+            Operation::Combinations => calculator.combinations(x, y),
+            Operation::Permutations => calculator.permutations(x, y),
+            Operation::Multinomial => calculator.multinomial(args),
+            Operation::Catalan => calculator.catalan(x),
+            Operation::StirlingFirst => calculator.stirling_first(x, y),
+            Operation::StirlingSecond => calculator.stirling_second(x, y),
+            Operation::Pi => Ok(std::f64::consts::PI),
+            Operation::E => Ok(std::f64::consts::E),
+            Operation::Abs | Operation::ToPolar => Ok(x.abs()),
+            Operation::FromPolar => Ok(x * calculator.cos(y, unit)),
+        }
+    }
+
+    fn format(&self, args: &[f64], unit: AngleUnit) -> String {
+        let x = args.first().copied().unwrap_or_default();
+        let y = args.get(1).copied().unwrap_or_default();
+        match self {
+            Operation::Add => format!("{} + {}", x, y),
+            Operation::Subtract => format!("{} - {}", x, y),
+            Operation::Multiply => format!("{} × {}", x, y),
+            Operation::Divide => format!("{} ÷ {}", x, y),
+            Operation::Power => format!("{} ^ {}", x, y),
+            Operation::Sqrt => format!("√{}", x),
+            Operation::Sin | Operation::Cos | Operation::Tan | Operation::Sec | Operation::Csc | Operation::Cot => {
+                format!("{}({})", self.name(), unit.label(x))
+            }
+            Operation::Asin | Operation::Acos | Operation::Atan => format!("{}({}) in {}", self.name(), x, unit),
+            Operation::Atan2 => format!("atan2({}, {}) in {}", x, y, unit),
+            Operation::Log10 => format!("log({})", x),
+            Operation::DegreesToRadians => format!("{}° → rad", x),
+            Operation::RadiansToDegrees => format!("{} rad → °", x),
+            Operation::Square => format!("{}²", x),
+            Operation::Reciprocal => format!("1/{}", x),
+            Operation::Factorial => format!("{}!", x),
+            Operation::Gamma => format!("Γ({})", x),
+            Operation::Beta => format!("B({}, {})", x, y),
+            Operation::DoubleFactorial => format!("{}!!", x),
+            Operation::Combinations => format!("C({}, {})", x, y),
+            Operation::Permutations => format!("P({}, {})", x, y),
+            Operation::Pi => "π".to_string(),
+            Operation::E => "e".to_string(),
+            Operation::Abs => format!("|{}|", x),
+            Operation::ToPolar => format!("{} → polar", x),
+            Operation::FromPolar => format!("{}∠{} → rectangular", x, unit.label(y)),
+            Operation::Multinomial | Operation::StirlingFirst | Operation::StirlingSecond => {
+                let args: Vec<String> = args.iter().map(f64::to_string).collect();
+                format!("{}({})", self.name(), args.join(", "))
+            }
+            _ => format!("{}({})", self.name(), x),
+        }
+    }
+
+    fn symbol(&self) -> &str {
+        match self {
+            Operation::Add => "+",
+            Operation::Subtract => "−",
+            Operation::Multiply => "×",
+            Operation::Divide => "÷",
+            Operation::Power => "xʸ",
+            Operation::Sqrt => "√",
+            Operation::Asin => "sin⁻¹",
+            Operation::Acos => "cos⁻¹",
+            Operation::Atan => "tan⁻¹",
+            Operation::Log10 => "log",
+            Operation::DegreesToRadians => "°→rad",
+            Operation::RadiansToDegrees => "rad→°",
+            Operation::Square => "x²",
+            Operation::Reciprocal => "1/x",
+            Operation::Factorial => "n!",
+            Operation::Gamma => "Γ",
+            Operation::Beta => "B",
+            Operation::DoubleFactorial => "n!!",
+            Operation::Combinations => "nCr",
+            Operation::Permutations => "nPr",
+            Operation::Pi => "π",
+            Operation::Abs => "|x|",
+            Operation::ToPolar => "→∠",
+            Operation::FromPolar => "∠→",
+            _ => self.name(),
+        }
+    }
+
+    fn domain(&self) -> Option<&str> {
+        let domain = match self {
+            Operation::Divide => "divisor ≠ 0",
+            Operation::Power => "a negative base needs an integer exponent; a zero base needs a non-negative one",
+            Operation::Sqrt => "value ≥ 0 (any value in complex mode)",
+            Operation::Tan | Operation::Sec => "angle is not an odd multiple of a quarter turn",
+            Operation::Csc | Operation::Cot => "angle is not a multiple of a half turn",
+            Operation::Asin | Operation::Acos => "-1 ≤ value ≤ 1",
+            Operation::Atan2 => "y and x are not both 0",
+            Operation::Acosh => "value ≥ 1",
+            Operation::Atanh => "-1 < value < 1",
+            Operation::Ln | Operation::Log10 => "value > 0 (any non-zero value in complex mode)",
+            Operation::Reciprocal => "value ≠ 0",
+            Operation::Factorial => "n is not a negative integer; integers up to 170 (10000 in exact mode)",
+            Operation::Gamma | Operation::LnGamma => "value is not 0 or a negative integer",
+            Operation::Beta => "neither a nor b is 0 or a negative integer",
+            Operation::DoubleFactorial => "integer n ≥ -1",
+            Operation::Combinations | Operation::Permutations => "integers 0 ≤ n, 0 ≤ r (any reals via the gamma function)",
+            Operation::Multinomial | Operation::Catalan => "non-negative integers",
+            Operation::StirlingFirst | Operation::StirlingSecond => "non-negative integers up to 1000",
+            _ => return None,
+        };
+        Some(domain)
+    }
+
+    fn example(&self) -> Vec<f64> {
+        match self {
+            Operation::Add | Operation::Subtract | Operation::Multiply => vec![6.0, 3.0],
+            Operation::Divide => vec![7.0, 2.0],
+            Operation::Power => vec![2.0, 10.0],
+            Operation::Sqrt | Operation::Square => vec![16.0],
+            Operation::Sin | Operation::Cos | Operation::Csc | Operation::Sec => vec![60.0],
+            Operation::Tan | Operation::Cot => vec![45.0],
+            Operation::Asin | Operation::Acos | Operation::Atanh => vec![0.5],
+            Operation::Atan | Operation::Acosh => vec![1.0],
+            Operation::Atan2 => vec![1.0, -1.0],
+            Operation::Ln => vec![std::f64::consts::E],
+            Operation::Log10 => vec![1000.0],
+            Operation::DegreesToRadians => vec![180.0],
+            Operation::RadiansToDegrees => vec![std::f64::consts::FRAC_PI_2],
+            Operation::Reciprocal => vec![4.0],
+            Operation::Factorial | Operation::Catalan => vec![5.0],
+            Operation::Gamma => vec![0.5],
+            Operation::LnGamma => vec![100.0],
+            Operation::Beta => vec![2.0, 3.0],
+            Operation::DoubleFactorial => vec![7.0],
+            Operation::Combinations | Operation::Permutations | Operation::StirlingFirst | Operation::StirlingSecond => {
+                vec![5.0, 2.0]
+            }
+            Operation::Multinomial => vec![2.0, 3.0, 1.0],
+            Operation::Abs => vec![-2.5],
+            Operation::FromPolar => vec![2.0, 30.0],
+            Operation::Pi | Operation::E => Vec::new(),
+            _ => vec![2.0],
+        }
+    }
+
+    fn error_operand(&self) -> Option<usize> {
+        match self {
+            Operation::Divide => Some(1),
+            _ => (self.arity() == 1).then_some(0),
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Operation {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Operation::ALL
+            .into_iter()
+            .find(|op| op.name() == s)
+            .ok_or_else(|| CalcError::UnknownOperation(s.to_string()))
+    }
+}
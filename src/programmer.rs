@@ -0,0 +1,315 @@
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::angle::AngleUnit;
+use crate::error::CalcError;
+use crate::registry::Operator;
+use crate::Calculator;
+
+/// Widths programmer mode can work in, in bits.
+pub const WORD_SIZES: [u32; 4] = [8, 16, 32, 64];
+
+/// The integer type programmer operations work in. Values are wrapped to
+/// the width with two's-complement semantics, then read back as signed or
+/// unsigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordSize {
+    bits: u32,
+    signed: bool,
+}
+
+impl WordSize {
+    pub fn new(bits: u32, signed: bool) -> Result<Self, CalcError> {
+        if !WORD_SIZES.contains(&bits) {
+            return Err(CalcError::InvalidRequest(format!(
+                "Word size must be 8, 16, 32 or 64 bits, not {}",
+                bits
+            )));
+        }
+        Ok(WordSize { bits, signed })
+    }
+
+    pub fn bits(self) -> u32 {
+        self.bits
+    }
+
+    pub fn signed(self) -> bool {
+        self.signed
+    }
+
+    fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.bits)
+    }
+
+    /// The bit pattern of `value` in this word, wrapping anything that does not fit.
+    pub fn wrap(self, value: i128) -> u64 {
+        value as u64 & self.mask()
+    }
+
+    /// The value a bit pattern stands for.
+    pub fn value(self, pattern: u64) -> i128 {
+        let pattern = pattern & self.mask();
+        if self.signed && pattern >> (self.bits - 1) & 1 == 1 {
+            pattern as i128 - (1i128 << self.bits)
+        } else {
+            pattern as i128
+        }
+    }
+
+    /// A bit pattern written in every base. Hex and binary are padded to the
+    /// full width so the sign bit is visible.
+    pub fn bases(self, pattern: u64) -> Bases {
+        let pattern = pattern & self.mask();
+        Bases {
+            decimal: self.value(pattern).to_string(),
+            hex: format!("0x{:0width$X}", pattern, width = self.bits as usize / 4),
+            octal: format!("0o{:o}", pattern),
+            binary: format!("0b{:0width$b}", pattern, width = self.bits as usize),
+        }
+    }
+}
+
+impl Default for WordSize {
+    fn default() -> Self {
+        WordSize { bits: 64, signed: true }
+    }
+}
+
+impl fmt::Display for WordSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let signedness = if self.signed { "signed" } else { "unsigned" };
+        write!(f, "{}-bit {}", self.bits, signedness)
+    }
+}
+
+/// A programmer-mode result in decimal, hexadecimal, octal and binary.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Bases {
+    pub decimal: String,
+    pub hex: String,
+    pub octal: String,
+    pub binary: String,
+}
+
+/// Parses an integer written in decimal or with a `0x`, `0o` or `0b`
+/// prefix, e.g. `-42`, `0xFF` or `0b1010_0101`. `None` if the text is not
+/// an integer or does not fit in 64 bits, signed or unsigned.
+pub fn parse_integer(text: &str) -> Option<i128> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x" | "0X") => (16, &digits[2..]),
+        Some("0o" | "0O") => (8, &digits[2..]),
+        Some("0b" | "0B") => (2, &digits[2..]),
+        _ => (10, digits),
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let magnitude = i128::from_str_radix(&digits, radix).ok()?;
+    let value = if negative { -magnitude } else { magnitude };
+    in_range(value).then_some(value)
+}
+
+/// An `f64` that holds an integer within the range `parse_integer` accepts.
+pub fn integer_from_f64(value: f64) -> Option<i128> {
+    (value.is_finite() && value.fract() == 0.0 && value.abs() < 2f64.powi(65))
+        .then_some(value as i128)
+        .filter(|value| in_range(*value))
+}
+
+fn in_range(value: i128) -> bool {
+    (i64::MIN as i128..=u64::MAX as i128).contains(&value)
+}
+
+/// The bitwise operations of programmer mode. Like [`Operation`](crate::Operation)
+/// they are registered by default, so expressions can call them; the
+/// server additionally evaluates them on exact integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+    ShiftLeft,
+    ShiftRight,
+    RotateLeft,
+    RotateRight,
+    Convert,
+}
+
+impl BitOperation {
+    pub const ALL: [BitOperation; 9] = [
+        BitOperation::And,
+        BitOperation::Or,
+        BitOperation::Xor,
+        BitOperation::Not,
+        BitOperation::ShiftLeft,
+        BitOperation::ShiftRight,
+        BitOperation::RotateLeft,
+        BitOperation::RotateRight,
+        BitOperation::Convert,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BitOperation::And => "and",
+            BitOperation::Or => "or",
+            BitOperation::Xor => "xor",
+            BitOperation::Not => "not",
+            BitOperation::ShiftLeft => "shl",
+            BitOperation::ShiftRight => "shr",
+            BitOperation::RotateLeft => "rol",
+            BitOperation::RotateRight => "ror",
+            BitOperation::Convert => "convert",
+        }
+    }
+
+    /// Applies the operation in `word` and returns the resulting bit
+    /// pattern. Shifts right are arithmetic for signed words and logical
+    /// for unsigned ones; shift counts must not be negative.
+    pub fn apply(self, word: WordSize, args: &[i128]) -> Result<u64, CalcError> {
+        let a = word.wrap(args.first().copied().unwrap_or_default());
+        let b = args.get(1).copied().unwrap_or_default();
+        let count = || -> Result<u32, CalcError> {
+            if b < 0 {
+                return Err(CalcError::Domain("Shift and rotate counts must not be negative".to_string()));
+            }
+            Ok(b.min(word.bits as i128) as u32)
+        };
+
+        let pattern = match self {
+            BitOperation::And => a & word.wrap(b),
+            BitOperation::Or => a | word.wrap(b),
+            BitOperation::Xor => a ^ word.wrap(b),
+            BitOperation::Not => !a,
+            BitOperation::ShiftLeft => a.checked_shl(count()?).unwrap_or(0),
+            BitOperation::ShiftRight => {
+                let count = count()?;
+                if word.signed {
+                    let shifted = word.value(a) >> count.min(word.bits - 1);
+                    word.wrap(shifted)
+                } else {
+                    a.checked_shr(count).unwrap_or(0)
+                }
+            }
+            BitOperation::RotateLeft | BitOperation::RotateRight => {
+                count()?;
+                let count = (b % word.bits as i128) as u32;
+                let count = if self == BitOperation::RotateLeft { count } else { (word.bits - count) % word.bits };
+                if count == 0 {
+                    a
+                } else {
+                    (a << count) | (a >> (word.bits - count))
+                }
+            }
+            BitOperation::Convert => a,
+        };
+        Ok(pattern & word.mask())
+    }
+
+    /// How the operation is written, given its operands as text.
+    pub fn describe(self, args: &[String]) -> String {
+        let a = args.first().map(String::as_str).unwrap_or_default();
+        let b = args.get(1).map(String::as_str).unwrap_or_default();
+        match self {
+            BitOperation::And => format!("{} AND {}", a, b),
+            BitOperation::Or => format!("{} OR {}", a, b),
+            BitOperation::Xor => format!("{} XOR {}", a, b),
+            BitOperation::Not => format!("NOT {}", a),
+            BitOperation::ShiftLeft => format!("{} << {}", a, b),
+            BitOperation::ShiftRight => format!("{} >> {}", a, b),
+            BitOperation::RotateLeft => format!("{} ROL {}", a, b),
+            BitOperation::RotateRight => format!("{} ROR {}", a, b),
+            BitOperation::Convert => a.to_string(),
+        }
+    }
+}
+
+impl Operator for BitOperation {
+    fn name(&self) -> &str {
+        BitOperation::name(*self)
+    }
+
+    fn operands(&self) -> &[&'static str] {
+        match self {
+            BitOperation::And | BitOperation::Or | BitOperation::Xor => &["first number", "second number"],
+            BitOperation::Not | BitOperation::Convert => &["value"],
+            _ => &["value", "bits"],
+        }
+    }
+
+    /// Runs in the calculator's word size and returns the wrapped value.
+    fn evaluate(&self, calculator: &Calculator, args: &[f64], _unit: AngleUnit) -> Result<f64, CalcError> {
+        let args = args
+            .iter()
+            .map(|&arg| integer_from_f64(arg))
+            .collect::<Option<Vec<i128>>>()
+            .ok_or_else(|| CalcError::Domain(format!("'{}' needs integer operands", self.name())))?;
+        let word = calculator.word_size();
+        Ok(word.value(self.apply(word, &args)?) as f64)
+    }
+
+    fn format(&self, args: &[f64], _unit: AngleUnit) -> String {
+        let args: Vec<String> = args.iter().map(f64::to_string).collect();
+        self.describe(&args)
+    }
+
+    fn symbol(&self) -> &str {
+        match self {
+            BitOperation::And => "AND",
+            BitOperation::Or => "OR",
+            BitOperation::Xor => "XOR",
+            BitOperation::Not => "NOT",
+            BitOperation::ShiftLeft => "<<",
+            BitOperation::ShiftRight => ">>",
+            BitOperation::RotateLeft => "ROL",
+            BitOperation::RotateRight => "ROR",
+            BitOperation::Convert => "BASE",
+        }
+    }
+
+    fn domain(&self) -> Option<&str> {
+        let domain = match self {
+            BitOperation::ShiftLeft
+            | BitOperation::ShiftRight
+            | BitOperation::RotateLeft
+            | BitOperation::RotateRight => "integers; bits ≥ 0",
+            _ => "integers, wrapped to the word size",
+        };
+        Some(domain)
+    }
+
+    fn example(&self) -> Vec<f64> {
+        match self {
+            BitOperation::And | BitOperation::Or | BitOperation::Xor => vec![12.0, 10.0],
+            BitOperation::Not => vec![5.0],
+            BitOperation::ShiftLeft | BitOperation::RotateLeft | BitOperation::RotateRight => vec![1.0, 4.0],
+            BitOperation::ShiftRight => vec![256.0, 4.0],
+            BitOperation::Convert => vec![255.0],
+        }
+    }
+}
+
+impl fmt::Display for BitOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for BitOperation {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BitOperation::ALL
+            .into_iter()
+            .find(|op| op.name() == s)
+            .ok_or_else(|| CalcError::UnknownOperation(s.to_string()))
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::angle::AngleUnit;
+use crate::distribution::DistributionOperation;
+use crate::error::CalcError;
+use crate::expr;
+use crate::number_theory::NumberOperation;
+use crate::operation::Operation;
+use crate::programmer::BitOperation;
+use crate::statistics::Statistic;
+use crate::Calculator;
+
+/// A real-valued operation frontends can look up by name. The built-in
+/// [`Operation`]s implement it, and callers can add their own with
+/// [`Registry::register`].
+pub trait Operator: Send + Sync {
+    /// Identifier used in requests, REPL menus and expressions.
+    fn name(&self) -> &str;
+
+    /// Human-readable operand names, in order. Their count is the arity.
+    fn operands(&self) -> &[&'static str];
+
+    fn arity(&self) -> usize {
+        self.operands().len()
+    }
+
+    /// Whether the last operand may repeat, so calls can pass more than
+    /// `arity()` arguments.
+    fn variadic(&self) -> bool {
+        false
+    }
+
+    /// Rejects operands outside the domain before `evaluate` runs. Built-ins
+    /// leave this to the `Calculator` method they call.
+    fn validate(&self, _args: &[f64]) -> Result<(), CalcError> {
+        Ok(())
+    }
+
+    /// Computes the result. `args` has exactly `arity()` values and has
+    /// passed `validate`; angles are in `unit`.
+    fn evaluate(&self, calculator: &Calculator, args: &[f64], unit: AngleUnit) -> Result<f64, CalcError>;
+
+    /// How the operation is written in history and responses, e.g. `sqrt(2)`.
+    fn format(&self, args: &[f64], _unit: AngleUnit) -> String {
+        let args: Vec<String> = args.iter().map(f64::to_string).collect();
+        format!("{}({})", self.name(), args.join(", "))
+    }
+
+    /// Short form for displays and keypads, e.g. `√`.
+    fn symbol(&self) -> &str {
+        self.name()
+    }
+
+    /// Plain-language restrictions on the operands, if there are any.
+    fn domain(&self) -> Option<&str> {
+        None
+    }
+
+    /// Operands that make a representative example.
+    fn example(&self) -> Vec<f64> {
+        vec![1.0; self.arity()]
+    }
+
+    /// Index of the operand an evaluation error is blamed on, if any.
+    fn error_operand(&self) -> Option<usize> {
+        (self.arity() == 1).then_some(0)
+    }
+}
+
+type Evaluate = dyn Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync;
+
+/// A custom operation backed by a closure, for example
+/// `Function::new("bmi", &["weight", "height"], |args| Ok(args[0] / (args[1] * args[1])))`.
+pub struct Function {
+    name: String,
+    operands: &'static [&'static str],
+    evaluate: Box<Evaluate>,
+    domain: Option<String>,
+    example: Option<Vec<f64>>,
+}
+
+impl Function {
+    pub fn new(
+        name: impl Into<String>,
+        operands: &'static [&'static str],
+        evaluate: impl Fn(&[f64]) -> Result<f64, CalcError> + Send + Sync + 'static,
+    ) -> Self {
+        Function {
+            name: name.into(),
+            operands,
+            evaluate: Box::new(evaluate),
+            domain: None,
+            example: None,
+        }
+    }
+
+    /// Describes the operands the closure accepts, for discovery clients.
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    pub fn with_example(mut self, operands: Vec<f64>) -> Self {
+        self.example = Some(operands);
+        self
+    }
+}
+
+impl Operator for Function {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn operands(&self) -> &[&'static str] {
+        self.operands
+    }
+
+    fn evaluate(&self, _calculator: &Calculator, args: &[f64], _unit: AngleUnit) -> Result<f64, CalcError> {
+        (self.evaluate)(args)
+    }
+
+    fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    fn example(&self) -> Vec<f64> {
+        self.example.clone().unwrap_or_else(|| vec![1.0; self.arity()])
+    }
+}
+
+/// The set of operations available to a [`Calculator`], in registration
+/// order. The default registry holds every built-in [`Operation`],
+/// [`BitOperation`], [`NumberOperation`] and [`DistributionOperation`].
+#[derive(Clone)]
+pub struct Registry {
+    operators: Vec<Arc<dyn Operator>>,
+}
+
+impl Registry {
+    /// Adds a custom operation. Names must be identifiers so expressions can
+    /// call them, and may not shadow an existing operation, a [`Statistic`],
+    /// which frontends look up by the same names, or the `log` alias.
+    pub fn register(&mut self, operator: impl Operator + 'static) -> Result<(), CalcError> {
+        let name = operator.name();
+        if !expr::is_identifier(name) {
+            return Err(CalcError::Registration(format!("'{}' is not a valid operation name", name)));
+        }
+        if self.get(name).is_some() {
+            return Err(CalcError::Registration(format!("Operation '{}' is already registered", name)));
+        }
+        if name.parse::<Statistic>().is_ok() {
+            return Err(CalcError::Registration(format!("'{}' is already a statistic", name)));
+        }
+        if name == expr::LOG10_ALIAS {
+            return Err(CalcError::Registration(format!("'{}' is reserved as an alias for log10", name)));
+        }
+        self.operators.push(Arc::new(operator));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Operator>> {
+        self.operators.iter().find(|operator| operator.name() == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Operator>> {
+        self.operators.iter()
+    }
+
+    /// Checks that `found` arguments suit the operator: exactly its arity,
+    /// or at least that many if it is variadic.
+    pub fn check_arity(operator: &dyn Operator, found: usize) -> Result<(), CalcError> {
+        let expected = operator.arity();
+        if found == expected || (operator.variadic() && found > expected) {
+            return Ok(());
+        }
+        Err(CalcError::ArgumentCount {
+            function: operator.name().to_string(),
+            expected,
+            found,
+        })
+    }
+
+    /// Checks arity and domain, then evaluates. Non-finite results from
+    /// custom operations are turned into errors here.
+    pub fn apply(
+        operator: &dyn Operator,
+        calculator: &Calculator,
+        args: &[f64],
+        unit: AngleUnit,
+    ) -> Result<f64, CalcError> {
+        Registry::check_arity(operator, args.len())?;
+        operator.validate(args)?;
+        let result = operator.evaluate(calculator, args, unit)?;
+        if result.is_nan() {
+            Err(CalcError::Domain(format!("'{}' has no real result for these operands", operator.name())))
+        } else if result.is_infinite() {
+            Err(CalcError::Overflow(format!("'{}' overflowed", operator.name())))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry {
+            operators: Operation::ALL
+                .into_iter()
+                .map(|operation| Arc::new(operation) as Arc<dyn Operator>)
+                .chain(BitOperation::ALL.into_iter().map(|operation| Arc::new(operation) as Arc<dyn Operator>))
+                .chain(NumberOperation::ALL.into_iter().map(|operation| Arc::new(operation) as Arc<dyn Operator>))
+                .chain(DistributionOperation::all().map(|operation| Arc::new(operation) as Arc<dyn Operator>))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.operators.iter().map(|operator| operator.name())).finish()
+    }
+}
@@ -0,0 +1,781 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use calculator::analysis::{self, Model};
+use calculator::expr::{self, Statement};
+use calculator::history::{self, ExportFormat, HistoryQuery};
+use calculator::number_theory::NumberOperation;
+use calculator::programmer::{self, BitOperation, WordSize};
+use calculator::{AngleUnit, Calculator, Operator, Register, Registry, Statistic};
+
+use crate::history_log::HistoryLog;
+
+/// The name REPL history is kept under in the history log.
+pub const REPL_SESSION: &str = "repl";
+
+/// The interactive menu-driven calculator. Input and output are injected so
+/// the REPL can be driven from a terminal, a piped file or a test buffer.
+pub struct Repl<'a, R, W> {
+    calculator: &'a mut Calculator,
+    input: R,
+    output: W,
+    history_log: Option<&'a HistoryLog>,
+}
+
+impl<'a, R: BufRead, W: Write> Repl<'a, R, W> {
+    pub fn new(calculator: &'a mut Calculator, input: R, output: W) -> Self {
+        Repl {
+            calculator,
+            input,
+            output,
+            history_log: None,
+        }
+    }
+
+    /// Appends every history entry recorded from here on to `log`.
+    pub fn with_history_log(mut self, log: &'a HistoryLog) -> Self {
+        self.calculator.track_unsaved_history();
+        self.history_log = Some(log);
+        self
+    }
+
+    /// Runs until the user exits or input ends at the main menu. Input that
+    /// ends in the middle of an operation is reported as `UnexpectedEof`.
+    pub fn run(&mut self) -> io::Result<()> {
+        writeln!(self.output, "Welcome to the Rust Scientific Calculator!")?;
+
+        loop {
+            self.show_menu()?;
+            let choice = match self.prompt("Enter your choice (0-15): ") {
+                Ok(choice) => choice,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    writeln!(self.output)?;
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            };
+
+            match choice.as_str() {
+                "1" => self.basic_operations()?,
+                "2" => self.number_theory_operations()?,
+                "3" => self.apply_operation("power")?,
+                "4" => self.apply_operation("sqrt")?,
+                "5" => self.trigonometric_operations()?,
+                "6" => self.logarithm_operations()?,
+                "7" => self.angle_conversion()?,
+                "8" => self.memory_operations()?,
+                "9" => self.history_operations()?,
+                "10" => self.all_operations()?,
+                "11" => self.evaluate_expression()?,
+                "12" => self.variable_operations()?,
+                "13" => self.programmer_operations()?,
+                "14" => self.statistics_operations()?,
+                "15" => self.curve_fitting()?,
+                "0" => {
+                    writeln!(self.output, "Thank you for using the calculator!")?;
+                    return Ok(());
+                }
+                _ => writeln!(self.output, "Invalid choice. Please try again.")?,
+            }
+            if let Some(log) = self.history_log {
+                log.append(REPL_SESSION, &self.calculator.take_unsaved_history())?;
+            }
+        }
+    }
+
+    fn show_menu(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Scientific Calculator ===")?;
+        writeln!(self.output, "1. Basic Operations (+, -, *, /)")?;
+        writeln!(self.output, "2. Number Theory")?;
+        writeln!(self.output, "3. Power")?;
+        writeln!(self.output, "4. Square Root")?;
+        writeln!(self.output, "5. Trigonometric Functions")?;
+        writeln!(self.output, "6. Logarithms")?;
+        writeln!(self.output, "7. Angle Conversion")?;
+        writeln!(self.output, "8. Memory Operations")?;
+        writeln!(self.output, "9. History")?;
+        writeln!(self.output, "10. All Operations")?;
+        writeln!(self.output, "11. Evaluate Expression")?;
+        writeln!(self.output, "12. Variables and Functions")?;
+        writeln!(self.output, "13. Programmer Mode")?;
+        writeln!(self.output, "14. Statistics")?;
+        writeln!(self.output, "15. Curve Fitting")?;
+        writeln!(self.output, "0. Exit")?;
+        writeln!(self.output, "=============================")
+    }
+
+    fn history_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== History ===")?;
+        writeln!(self.output, "1. Show History")?;
+        writeln!(self.output, "2. Search History")?;
+        writeln!(self.output, "3. Export History")?;
+        writeln!(self.output, "4. Clear History")?;
+        let history_choice = self.prompt("Choose operation (1-4): ")?;
+
+        match history_choice.as_str() {
+            "1" => self.show_history(&HistoryQuery::default()),
+            "2" => {
+                let query = self.history_query()?;
+                self.show_history(&query)
+            }
+            "3" => self.export_history(),
+            "4" => {
+                self.calculator.clear_history();
+                if let Some(log) = self.history_log {
+                    log.record_clear(REPL_SESSION)?;
+                }
+                writeln!(self.output, "History cleared")
+            }
+            _ => writeln!(self.output, "Invalid history operation choice"),
+        }
+    }
+
+    fn show_history(&mut self, query: &HistoryQuery) -> io::Result<()> {
+        writeln!(self.output, "\n=== Calculation History ===")?;
+        let lines: Vec<String> = self
+            .calculator
+            .history()
+            .search(query)
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| format!("{}. [{}] {}", i + 1, entry.time(), entry))
+            .collect();
+        if lines.is_empty() {
+            writeln!(self.output, "No operations in history")?;
+        }
+        for line in lines {
+            writeln!(self.output, "{}", line)?;
+        }
+        writeln!(self.output, "==========================\n")
+    }
+
+    /// Asks for search text and a date range; blank answers match anything.
+    /// Asks again until the dates parse.
+    fn history_query(&mut self) -> io::Result<HistoryQuery> {
+        loop {
+            let text = self.prompt("Text to find (blank for any): ")?;
+            let from = self.prompt("From date YYYY-MM-DD (blank for any): ")?;
+            let to = self.prompt("To date YYYY-MM-DD (blank for any): ")?;
+            let optional = |answer: &str| Some(answer.to_string()).filter(|answer| !answer.is_empty());
+            match HistoryQuery::parse(Some(&text), optional(&from).as_deref(), optional(&to).as_deref()) {
+                Ok(query) => return Ok(query),
+                Err(e) => writeln!(self.output, "Error: {}", e)?,
+            }
+        }
+    }
+
+    fn export_history(&mut self) -> io::Result<()> {
+        let format = match self.prompt("Format (json/csv): ")?.parse::<ExportFormat>() {
+            Ok(format) => format,
+            Err(e) => return writeln!(self.output, "Error: {}", e),
+        };
+        let query = self.history_query()?;
+        let path = self.prompt("Save to file (blank to print): ")?;
+
+        let export = history::export(&self.calculator.history().search(&query), format);
+        if path.is_empty() {
+            return write!(self.output, "{}", export);
+        }
+        match fs::write(&path, export) {
+            Ok(()) => writeln!(self.output, "History exported to {}", path),
+            Err(e) => writeln!(self.output, "Error: could not write {}: {}", path, e),
+        }
+    }
+
+    /// Prompts for each operand of a registered operation, evaluates it and
+    /// records the result in the history.
+    fn apply_operation(&mut self, name: &str) -> io::Result<()> {
+        let Some(operator) = self.calculator.registry().get(name).cloned() else {
+            return writeln!(self.output, "Unknown operation '{}'", name);
+        };
+
+        let unit = self.calculator.angle_unit();
+        let mut args = Vec::with_capacity(operator.arity());
+        if operator.variadic() {
+            let operands = operator.operands().join(", ");
+            args = self.get_values(&format!("Enter {}, ... separated by commas or spaces: ", operands))?;
+        } else {
+            for &operand in operator.operands() {
+                let prompt = match operand {
+                    "angle" => format!("Enter angle in {}: ", unit),
+                    _ => format!("Enter {}: ", operand),
+                };
+                args.push(self.get_number(&prompt)?);
+            }
+        }
+
+        let expression = operator.format(&args, unit);
+        match Registry::apply(operator.as_ref(), self.calculator, &args, unit) {
+            Ok(value) => {
+                self.calculator.record_result(operator.name(), &expression, value);
+                writeln!(self.output, "Result: {}", value)
+            }
+            Err(e) => {
+                self.calculator.record_failure(operator.name(), &expression, &e);
+                writeln!(self.output, "Error: {}", e)
+            }
+        }
+    }
+
+    /// Evaluates an expression, or runs an assignment such as `r = 2.5` or a
+    /// definition such as `f(x) = x^2 + 1`.
+    fn evaluate_expression(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Evaluate Expression ===")?;
+        writeln!(self.output, "Enter an expression such as 'pi * r^2', assign with 'r = 2.5'")?;
+        writeln!(self.output, "or define a function with 'f(x, y) = x^2 + 3*x*y'")?;
+        let input = self.prompt("> ")?;
+
+        let unit = self.calculator.angle_unit();
+        let statement = match expr::parse_statement(&input) {
+            Ok(statement) => statement,
+            Err(e) => return writeln!(self.output, "Error: {}", e),
+        };
+        match statement.execute(self.calculator, unit) {
+            Ok(Some(value)) => {
+                self.calculator.record_result("evaluate", &input, value);
+                writeln!(self.output, "Result: {}", value)
+            }
+            Ok(None) => match &statement {
+                Statement::Definition { function, .. } => writeln!(self.output, "Defined {}", function.source),
+                _ => Ok(()),
+            },
+            Err(e) => {
+                self.calculator.record_failure("evaluate", &input, &e);
+                writeln!(self.output, "Error: {}", e)
+            }
+        }
+    }
+
+    fn variable_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Variables and Functions ===")?;
+        writeln!(self.output, "1. List Variables")?;
+        writeln!(self.output, "2. Inspect Variable")?;
+        writeln!(self.output, "3. Delete Variable")?;
+        writeln!(self.output, "4. List Functions")?;
+        writeln!(self.output, "5. Delete Function")?;
+        let var_choice = self.prompt("Choose operation (1-5): ")?;
+
+        match var_choice.as_str() {
+            "1" => {
+                if self.calculator.variables().is_empty() {
+                    return writeln!(self.output, "No variables defined");
+                }
+                let variables: Vec<String> = self
+                    .calculator
+                    .variables()
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect();
+                for variable in variables {
+                    writeln!(self.output, "  {}", variable)?;
+                }
+                Ok(())
+            }
+            "2" => {
+                let name = self.prompt("Variable name: ")?;
+                match self.calculator.variable(&name) {
+                    Some(value) => writeln!(self.output, "{} = {}", name, value),
+                    None => writeln!(self.output, "Unknown variable '{}'", name),
+                }
+            }
+            "3" => {
+                let name = self.prompt("Variable name: ")?;
+                match self.calculator.remove_variable(&name) {
+                    Some(_) => writeln!(self.output, "Deleted '{}'", name),
+                    None => writeln!(self.output, "Unknown variable '{}'", name),
+                }
+            }
+            "4" => {
+                if self.calculator.functions().is_empty() {
+                    return writeln!(self.output, "No functions defined");
+                }
+                let functions: Vec<String> =
+                    self.calculator.functions().values().map(|function| function.source.clone()).collect();
+                for function in functions {
+                    writeln!(self.output, "  {}", function)?;
+                }
+                Ok(())
+            }
+            "5" => {
+                let name = self.prompt("Function name: ")?;
+                match self.calculator.remove_function(&name) {
+                    Some(_) => writeln!(self.output, "Deleted '{}'", name),
+                    None => writeln!(self.output, "Unknown function '{}'", name),
+                }
+            }
+            _ => writeln!(self.output, "Invalid variable operation choice"),
+        }
+    }
+
+    fn programmer_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Programmer Mode ({}) ===", self.calculator.word_size())?;
+        writeln!(self.output, "Integers may be entered as 42, -7, 0xFF, 0o17 or 0b1010")?;
+        writeln!(self.output, "1. AND          4. NOT            7. Rotate Left")?;
+        writeln!(self.output, "2. OR           5. Shift Left     8. Rotate Right")?;
+        writeln!(self.output, "3. XOR          6. Shift Right    9. Convert Base")?;
+        writeln!(self.output, "10. Change word size")?;
+        let prog_choice = self.prompt("Choose operation (1-10): ")?;
+
+        let operation = match prog_choice.as_str() {
+            "1" => BitOperation::And,
+            "2" => BitOperation::Or,
+            "3" => BitOperation::Xor,
+            "4" => BitOperation::Not,
+            "5" => BitOperation::ShiftLeft,
+            "6" => BitOperation::ShiftRight,
+            "7" => BitOperation::RotateLeft,
+            "8" => BitOperation::RotateRight,
+            "9" => BitOperation::Convert,
+            "10" => return self.word_size_setting(),
+            _ => return writeln!(self.output, "Invalid programmer operation choice"),
+        };
+
+        let mut args = Vec::with_capacity(operation.arity());
+        for &operand in operation.operands() {
+            args.push(self.get_integer(&format!("Enter {}: ", operand))?);
+        }
+
+        let word = self.calculator.word_size();
+        let operands: Vec<String> = args.iter().map(i128::to_string).collect();
+        let expression = format!("{} ({})", operation.describe(&operands), word);
+        match operation.apply(word, &args) {
+            Ok(pattern) => {
+                let bases = self.calculator.record_word_result(operation.name(), &expression, word, pattern);
+                writeln!(self.output, "DEC: {}", bases.decimal)?;
+                writeln!(self.output, "HEX: {}", bases.hex)?;
+                writeln!(self.output, "OCT: {}", bases.octal)?;
+                writeln!(self.output, "BIN: {}", bases.binary)
+            }
+            Err(e) => {
+                self.calculator.record_failure(operation.name(), &expression, &e);
+                writeln!(self.output, "Error: {}", e)
+            }
+        }
+    }
+
+    fn statistics_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Statistics ===")?;
+        writeln!(self.output, "1. Count        6. Median                 11. Sample Std Dev")?;
+        writeln!(self.output, "2. Sum          7. Mode                   12. Quartiles")?;
+        writeln!(self.output, "3. Minimum      8. Population Variance    13. Percentile")?;
+        writeln!(self.output, "4. Maximum      9. Sample Variance        14. Skewness")?;
+        writeln!(self.output, "5. Mean         10. Population Std Dev    15. Kurtosis")?;
+        let stat_choice = self.prompt("Choose statistic (1-15): ")?;
+
+        let statistic = match stat_choice.parse::<usize>() {
+            Ok(choice @ 1..=15) => Statistic::ALL[choice - 1],
+            _ => return writeln!(self.output, "Invalid statistic choice"),
+        };
+        let values = self.get_values("Enter values separated by commas or spaces: ")?;
+        let percentile = match statistic {
+            Statistic::Percentile => Some(self.get_number("Enter percentile (0-100): ")?),
+            _ => None,
+        };
+
+        let expression = statistic.describe(&values, percentile);
+        match statistic.compute(&values, percentile) {
+            Ok(results) => {
+                self.calculator.record_statistic(statistic.name(), &expression, &results);
+                match statistic {
+                    Statistic::Quartiles => {
+                        for (quartile, result) in results.iter().enumerate() {
+                            writeln!(self.output, "Q{}: {}", quartile + 1, result)?;
+                        }
+                        Ok(())
+                    }
+                    _ => {
+                        let results: Vec<String> = results.iter().map(f64::to_string).collect();
+                        writeln!(self.output, "{} = {}", expression, results.join(", "))
+                    }
+                }
+            }
+            Err(e) => {
+                self.calculator.record_failure(statistic.name(), &expression, &e);
+                writeln!(self.output, "Error: {}", e)
+            }
+        }
+    }
+
+    fn curve_fitting(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Curve Fitting ===")?;
+        writeln!(self.output, "1. Linear        y = a + b*x")?;
+        writeln!(self.output, "2. Polynomial    y = c0 + c1*x + ... + cn*x^n")?;
+        writeln!(self.output, "3. Exponential   y = a*e^(b*x)")?;
+        writeln!(self.output, "4. Logarithmic   y = a + b*ln(x)")?;
+        writeln!(self.output, "5. Power         y = a*x^b")?;
+        let fit_choice = self.prompt("Choose model (1-5): ")?;
+
+        let model = match fit_choice.as_str() {
+            "1" => Ok(Model::Linear),
+            "2" => {
+                let degree = self.prompt(&format!("Degree (1-{}): ", analysis::MAX_DEGREE))?;
+                Model::new("polynomial", Some(degree.parse().unwrap_or_default()))
+            }
+            "3" => Ok(Model::Exponential),
+            "4" => Ok(Model::Logarithmic),
+            "5" => Ok(Model::Power),
+            _ => return writeln!(self.output, "Invalid model choice"),
+        };
+        let model = match model {
+            Ok(model) => model,
+            Err(e) => return writeln!(self.output, "Error: {}", e),
+        };
+        let x = self.get_values("Enter x values separated by commas or spaces: ")?;
+        let y = self.get_values("Enter y values separated by commas or spaces: ")?;
+        let name = self.prompt(&format!("Function name (blank for {}): ", analysis::DEFAULT_FUNCTION))?;
+        let name = if name.is_empty() { analysis::DEFAULT_FUNCTION } else { name.as_str() };
+
+        let fit = match analysis::fit(model, &x, &y) {
+            Ok(fit) => fit,
+            Err(e) => return writeln!(self.output, "Error: {}", e),
+        };
+        writeln!(self.output, "y = {}", fit.equation())?;
+        writeln!(self.output, "R² = {}", fit.r_squared)?;
+        let residuals: Vec<String> = fit.residuals.iter().map(f64::to_string).collect();
+        writeln!(self.output, "Residuals: {}", residuals.join(", "))?;
+        match fit.function(name).and_then(|function| self.calculator.define_function(name, function)) {
+            Ok(()) => writeln!(self.output, "Use {}(x) in expressions to predict new values", name),
+            Err(e) => writeln!(self.output, "Could not define {}: {}", name, e),
+        }
+    }
+
+    fn word_size_setting(&mut self) -> io::Result<()> {
+        writeln!(self.output, "Current word size: {}", self.calculator.word_size())?;
+        let bits = self.prompt("Bits (8, 16, 32 or 64): ")?;
+        let signed = self.prompt("Signed? (y/n): ")?;
+        let signed = !signed.eq_ignore_ascii_case("n") && !signed.eq_ignore_ascii_case("no");
+        let word_size = bits
+            .parse()
+            .map_err(|_| format!("Invalid word size '{}'", bits))
+            .and_then(|bits| WordSize::new(bits, signed).map_err(|e| e.to_string()));
+        match word_size {
+            Ok(word_size) => {
+                self.calculator.set_word_size(word_size);
+                writeln!(self.output, "Word size set to {}", word_size)
+            }
+            Err(e) => writeln!(self.output, "Error: {}", e),
+        }
+    }
+
+    fn basic_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Basic Operations ===")?;
+        writeln!(self.output, "1. Addition (+)")?;
+        writeln!(self.output, "2. Subtraction (-)")?;
+        writeln!(self.output, "3. Multiplication (*)")?;
+        writeln!(self.output, "4. Division (/)")?;
+        let op_choice = self.prompt("Choose operation (1-4): ")?;
+
+        let name = match op_choice.as_str() {
+            "1" => "add",
+            "2" => "subtract",
+            "3" => "multiply",
+            "4" => "divide",
+            _ => return writeln!(self.output, "Invalid operation choice"),
+        };
+        self.apply_operation(name)
+    }
+
+    fn number_theory_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Number Theory ===")?;
+        writeln!(self.output, "Integers may be entered as 42, -7, 0xFF, 0o17 or 0b1010")?;
+        writeln!(self.output, "1. GCD                5. Modular Power      9. Next Prime")?;
+        writeln!(self.output, "2. LCM                6. Modular Inverse   10. Previous Prime")?;
+        writeln!(self.output, "3. Mod (floored)      7. Primality Test    11. Euler's Totient")?;
+        writeln!(self.output, "4. Rem (truncated)    8. Factorize         12. Divisors")?;
+        let nt_choice = self.prompt("Choose operation (1-12): ")?;
+
+        let operation = match nt_choice.parse::<usize>() {
+            Ok(choice @ 1..=12) => NumberOperation::ALL[choice - 1],
+            _ => return writeln!(self.output, "Invalid number theory operation choice"),
+        };
+
+        let mut args = Vec::with_capacity(operation.arity());
+        for &operand in operation.operands() {
+            args.push(self.get_integer(&format!("Enter {}: ", operand))?);
+        }
+
+        let operands: Vec<String> = args.iter().map(i128::to_string).collect();
+        let expression = operation.describe(&operands);
+        match operation.apply(&args) {
+            Ok(results) => {
+                self.calculator.record_integers(operation, &expression, &results);
+                writeln!(self.output, "{} = {}", expression, operation.describe_results(&results))
+            }
+            Err(e) => {
+                self.calculator.record_failure(operation.name(), &expression, &e);
+                writeln!(self.output, "Error: {}", e)
+            }
+        }
+    }
+
+    fn trigonometric_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Trigonometric Functions ===")?;
+        writeln!(self.output, "1. Sine            7. Inverse Sine         13. Hyperbolic Tangent")?;
+        writeln!(self.output, "2. Cosine          8. Inverse Cosine       14. Inverse Hyperbolic Sine")?;
+        writeln!(self.output, "3. Tangent         9. Inverse Tangent      15. Inverse Hyperbolic Cosine")?;
+        writeln!(self.output, "4. Secant         10. Two-argument atan2   16. Inverse Hyperbolic Tangent")?;
+        writeln!(
+            self.output,
+            "5. Cosecant       11. Hyperbolic Sine      17. Change angle unit (current: {})",
+            self.calculator.angle_unit()
+        )?;
+        writeln!(self.output, "6. Cotangent      12. Hyperbolic Cosine")?;
+        let func_choice = self.prompt("Choose function (1-17): ")?;
+
+        let name = match func_choice.as_str() {
+            "1" => "sin",
+            "2" => "cos",
+            "3" => "tan",
+            "4" => "sec",
+            "5" => "csc",
+            "6" => "cot",
+            "7" => "asin",
+            "8" => "acos",
+            "9" => "atan",
+            "10" => "atan2",
+            "11" => "sinh",
+            "12" => "cosh",
+            "13" => "tanh",
+            "14" => "asinh",
+            "15" => "acosh",
+            "16" => "atanh",
+            "17" => return self.angle_unit_setting(),
+            _ => return writeln!(self.output, "Invalid function choice"),
+        };
+        self.apply_operation(name)
+    }
+
+    fn angle_unit_setting(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Angle Unit ===")?;
+        writeln!(self.output, "1. Degrees")?;
+        writeln!(self.output, "2. Radians")?;
+        writeln!(self.output, "3. Gradians")?;
+        let unit_choice = self.prompt("Choose unit (1-3): ")?;
+
+        let unit = match unit_choice.as_str() {
+            "1" => AngleUnit::Degrees,
+            "2" => AngleUnit::Radians,
+            "3" => AngleUnit::Gradians,
+            _ => return writeln!(self.output, "Invalid unit choice"),
+        };
+        self.calculator.set_angle_unit(unit);
+        writeln!(self.output, "Angle unit set to {}", unit)
+    }
+
+    fn logarithm_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Logarithm Functions ===")?;
+        writeln!(self.output, "1. Natural Logarithm (ln)")?;
+        writeln!(self.output, "2. Base-10 Logarithm (log10)")?;
+        let func_choice = self.prompt("Choose function (1-2): ")?;
+
+        let name = match func_choice.as_str() {
+            "1" => "ln",
+            "2" => "log10",
+            _ => return writeln!(self.output, "Invalid function choice"),
+        };
+        self.apply_operation(name)
+    }
+
+    fn angle_conversion(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Angle Conversion ===")?;
+        writeln!(self.output, "1. Degrees to Radians")?;
+        writeln!(self.output, "2. Radians to Degrees")?;
+        let conv_choice = self.prompt("Choose conversion (1-2): ")?;
+
+        let name = match conv_choice.as_str() {
+            "1" => "degrees_to_radians",
+            "2" => "radians_to_degrees",
+            _ => return writeln!(self.output, "Invalid conversion choice"),
+        };
+        self.apply_operation(name)
+    }
+
+    /// Lists every registered operation, custom ones included, and runs the
+    /// one the user names.
+    fn all_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== All Operations ===")?;
+        let signatures: Vec<String> = self
+            .calculator
+            .registry()
+            .iter()
+            .map(|operator| format!("{}({})", operator.name(), operator.operands().join(", ")))
+            .collect();
+        for signature in signatures {
+            writeln!(self.output, "  {}", signature)?;
+        }
+        let name = self.prompt("Operation name: ")?;
+        self.apply_operation(&name)
+    }
+
+    fn memory_operations(&mut self) -> io::Result<()> {
+        writeln!(self.output, "\n=== Memory Operations ===")?;
+        writeln!(self.output, "1. MS - Store in Memory")?;
+        writeln!(self.output, "2. MR - Recall from Memory")?;
+        writeln!(self.output, "3. M+ - Add to Memory")?;
+        writeln!(self.output, "4. M- - Subtract from Memory")?;
+        writeln!(self.output, "5. MC - Clear Memory")?;
+        writeln!(self.output, "6. Show All Registers")?;
+        writeln!(self.output, "7. Clear All Registers")?;
+        let mem_choice = self.prompt("Choose operation (1-7): ")?;
+
+        match mem_choice.as_str() {
+            "1" => {
+                let register = self.get_register()?;
+                let value = self.get_number("Enter value to store: ")?;
+                self.calculator.store_memory(register, value);
+                writeln!(self.output, "Value stored in {}", register)
+            }
+            "2" => {
+                let register = self.get_register()?;
+                let value = self.calculator.recall_memory(register);
+                self.calculator.set_answer(value);
+                writeln!(self.output, "{} = {} (now available as ans)", register, value)
+            }
+            "3" => {
+                let register = self.get_register()?;
+                let value = self.get_number("Enter value to add: ")?;
+                self.calculator.add_to_memory(register, value);
+                writeln!(self.output, "{} = {}", register, self.calculator.recall_memory(register))
+            }
+            "4" => {
+                let register = self.get_register()?;
+                let value = self.get_number("Enter value to subtract: ")?;
+                self.calculator.subtract_from_memory(register, value);
+                writeln!(self.output, "{} = {}", register, self.calculator.recall_memory(register))
+            }
+            "5" => {
+                let register = self.get_register()?;
+                self.calculator.clear_memory(register);
+                writeln!(self.output, "{} cleared", register)
+            }
+            "6" => {
+                let registers: Vec<String> = self
+                    .calculator
+                    .memory()
+                    .iter()
+                    .map(|(register, value)| format!("{:>3} = {}", register, value))
+                    .collect();
+                for register in registers {
+                    writeln!(self.output, "  {}", register)?;
+                }
+                Ok(())
+            }
+            "7" => {
+                self.calculator.clear_all_memory();
+                writeln!(self.output, "All registers cleared")
+            }
+            _ => writeln!(self.output, "Invalid memory operation choice"),
+        }
+    }
+
+    /// Reads a register name, asking again until it is `M` or `M1` to `M9`.
+    fn get_register(&mut self) -> io::Result<Register> {
+        loop {
+            match self.prompt("Register (M or M1-M9, blank for M): ")?.parse() {
+                Ok(register) => return Ok(register),
+                Err(e) => writeln!(self.output, "{}. Please try again.", e)?,
+            }
+        }
+    }
+
+    /// Writes `prompt` and reads one trimmed line. End of input is an
+    /// `UnexpectedEof` error so no caller can spin on an empty stream.
+    fn prompt(&mut self, prompt: &str) -> io::Result<String> {
+        write!(self.output, "{}", prompt)?;
+        self.output.flush()?;
+
+        let mut input = String::new();
+        if self.input.read_line(&mut input)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input ended unexpectedly"));
+        }
+        Ok(input.trim().to_string())
+    }
+
+    /// Reads a programmer-mode integer in any base. Anything else is
+    /// evaluated as an expression that must come out as an integer.
+    fn get_integer(&mut self, prompt: &str) -> io::Result<i128> {
+        loop {
+            let input = self.prompt(prompt)?;
+            if let Some(integer) = programmer::parse_integer(&input) {
+                return Ok(integer);
+            }
+            let unit = self.calculator.angle_unit();
+            match expr::parse(&input).and_then(|ast| ast.evaluate(self.calculator, unit)) {
+                Ok(number) => match programmer::integer_from_f64(number) {
+                    Some(integer) => return Ok(integer),
+                    None => writeln!(self.output, "{} is not a 64-bit integer. Please try again.", number)?,
+                },
+                Err(e) => writeln!(self.output, "Invalid integer ({}). Please try again.", e)?,
+            }
+        }
+    }
+
+    /// Reads a data set of plain numbers separated by commas or whitespace.
+    fn get_values(&mut self, prompt: &str) -> io::Result<Vec<f64>> {
+        loop {
+            let input = self.prompt(prompt)?;
+            let values = input
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse::<f64>().map_err(|_| value))
+                .collect::<Result<Vec<f64>, _>>();
+            match values {
+                Ok(values) => return Ok(values),
+                Err(value) => writeln!(self.output, "'{}' is not a number. Please try again.", value)?,
+            }
+        }
+    }
+
+    /// Reads a number. Anything that is not a plain number is evaluated as
+    /// an expression, so `ans` or `2 * r` work wherever a number is asked for.
+    fn get_number(&mut self, prompt: &str) -> io::Result<f64> {
+        loop {
+            let input = self.prompt(prompt)?;
+            if let Ok(number) = input.parse::<f64>() {
+                return Ok(number);
+            }
+            let unit = self.calculator.angle_unit();
+            match expr::parse(&input).and_then(|ast| ast.evaluate(self.calculator, unit)) {
+                Ok(number) => return Ok(number),
+                Err(e) => writeln!(self.output, "Invalid number ({}). Please try again.", e)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    /// Runs the REPL over `input`, returning its result and everything it wrote.
+    fn run(input: &str) -> (io::Result<()>, String) {
+        let mut calculator = Calculator::with_registry(Arc::new(Registry::default()));
+        let mut output = Vec::new();
+        let result = Repl::new(&mut calculator, Cursor::new(input), &mut output).run();
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn input_ending_at_the_main_menu_exits_cleanly() {
+        let (result, output) = run("");
+        assert!(result.is_ok());
+        assert!(output.ends_with("Enter your choice (0-15): \n"));
+    }
+
+    #[test]
+    fn input_ending_mid_operation_is_unexpected() {
+        let (result, output) = run("1\n1\n2\n");
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert!(output.ends_with("Enter second number: "));
+    }
+
+    #[test]
+    fn scripted_addition_prints_its_result() {
+        let (result, output) = run("1\n1\n2\n3\n0\n");
+        assert!(result.is_ok());
+        assert!(output.contains("Enter first number: Enter second number: Result: 5\n"));
+        assert!(output.ends_with("Thank you for using the calculator!\n"));
+    }
+}
@@ -0,0 +1,1623 @@
+use axum::{
+    extract::{Json, Path, Query, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderName, Method, StatusCode,
+    },
+    middleware,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Extension, Router,
+};
+use calculator::analysis::{self, Model};
+use calculator::expr::{self, Statement, UserFunction};
+use calculator::history::{self, ExportFormat, HistoryQuery};
+use calculator::number_theory::NumberOperation;
+use calculator::programmer::{self, Bases, BitOperation, WordSize};
+use calculator::{
+    AngleUnit, BigInt, CalcError, Calculator, Complex, HistoryEntry, Operation, Operator, Register, Registry,
+    Statistic,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::{schema::Type, ObjectBuilder, RefOr, Schema};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use tower_http::{
+    cors::{Any, CorsLayer},
+    services::ServeDir,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::cli::HistoryOptions;
+use crate::history_log::HistoryLog;
+use crate::session::{self, AppState, SessionId};
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct CalculationRequest {
+    /// Name of a registered operation (see `/api/operations`) or of a
+    /// statistic such as `mean`, which reads `values` instead.
+    operation: String,
+    a: Option<Operand>,
+    b: Option<Operand>,
+    value: Option<Operand>,
+    /// Operands in order, for operations taking more than two; also accepted
+    /// in place of `a`/`b` or `value`.
+    args: Option<Vec<Operand>>,
+    /// Compute integer operations exactly and return the decimal digits in `exact`.
+    #[serde(default)]
+    exact: bool,
+    /// Treat operands as complex numbers; the `*_im` fields carry imaginary parts.
+    #[serde(default)]
+    complex: bool,
+    a_im: Option<f64>,
+    b_im: Option<f64>,
+    value_im: Option<f64>,
+    /// Overrides the session's angle unit for this request.
+    angle_unit: Option<AngleUnit>,
+    /// Overrides the session's word size (8, 16, 32 or 64 bits) for programmer operations.
+    word_size: Option<u32>,
+    /// Overrides whether programmer operations treat the word as signed.
+    signed: Option<bool>,
+    /// The data set for statistics such as `mean` or `quartiles`.
+    values: Option<Vec<f64>>,
+    /// Which percentile (0 to 100) the `percentile` statistic computes.
+    percentile: Option<f64>,
+}
+
+/// Longest decimal integer, in digits, accepted as an exact-mode string
+/// operand, the same as the largest result exact mode produces. Longer
+/// ones would make even a single multiplication slow.
+const MAX_EXACT_OPERAND_DIGITS: usize = 100_000;
+
+/// A numeric operand. Clients may send a JSON number or, for integers too
+/// large to survive the trip through an `f64`, a string. Strings may also be
+/// integers in hex, octal or binary, e.g. `"0xFF"`.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(untagged)]
+enum Operand {
+    Number(f64),
+    Text(String),
+}
+
+impl Operand {
+    /// The operand as a finite `f64`; strings such as `"NaN"`, `"inf"` or
+    /// `"1e400"` that Rust would parse to something else are refused.
+    fn to_f64(&self, field: &'static str) -> Result<f64, CalcError> {
+        match self {
+            Operand::Number(n) => Some(*n),
+            Operand::Text(text) => text
+                .trim()
+                .parse()
+                .ok()
+                .or_else(|| programmer::parse_integer(text).map(|n| n as f64)),
+        }
+        .filter(|n: &f64| n.is_finite())
+        .ok_or(CalcError::InvalidOperand(field))
+    }
+
+    /// The operand as a programmer-mode integer, which must fit in 64 bits.
+    fn to_word_integer(&self, field: &'static str) -> Result<i128, CalcError> {
+        match self {
+            Operand::Number(n) => programmer::integer_from_f64(*n),
+            Operand::Text(text) => programmer::parse_integer(text),
+        }
+        .ok_or(CalcError::InvalidOperand(field))
+    }
+
+    /// The operand as an exact integer, or `None` if it is not one. Strings
+    /// longer than `MAX_EXACT_OPERAND_DIGITS` are refused before parsing.
+    fn to_integer(&self, field: &'static str) -> Result<Option<BigInt>, CalcError> {
+        match self {
+            Operand::Number(n) => Ok(BigInt::from_f64(*n)),
+            Operand::Text(text) if text.trim().trim_start_matches(['-', '+']).len() > MAX_EXACT_OPERAND_DIGITS => {
+                Err(CalcError::OperandTooLarge { field, max_digits: MAX_EXACT_OPERAND_DIGITS })
+            }
+            Operand::Text(text) => Ok(BigInt::parse(text)),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Number(n) => write!(f, "{}", n),
+            Operand::Text(text) => write!(f, "{}", text.trim()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct EvaluateRequest {
+    /// An expression such as `pi * r^2`, an assignment such as `r = 2.5` or
+    /// a function definition such as `f(x, y) = x^2 + 3*x*y`.
+    expression: String,
+    angle_unit: Option<AngleUnit>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SettingsPayload {
+    angle_unit: AngleUnit,
+    /// Bits in a programmer-mode word: 8, 16, 32 or 64.
+    word_size: u32,
+    signed: bool,
+}
+
+impl SettingsPayload {
+    fn new(calculator: &Calculator) -> Self {
+        SettingsPayload {
+            angle_unit: calculator.angle_unit(),
+            word_size: calculator.word_size().bits(),
+            signed: calculator.word_size().signed(),
+        }
+    }
+}
+
+/// Settings to change; omitted ones keep their current value.
+#[derive(Debug, Deserialize, ToSchema)]
+struct SettingsUpdate {
+    angle_unit: Option<AngleUnit>,
+    word_size: Option<u32>,
+    signed: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct MemoryRequest {
+    value: f64,
+    /// `M` (the default) or `M1` to `M9`.
+    register: Option<Register>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct RegisterRequest {
+    /// `M` (the default) or `M1` to `M9`.
+    register: Option<Register>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+struct ClearMemoryRequest {
+    /// `M` (the default) or `M1` to `M9`.
+    register: Option<Register>,
+    /// Clear every register instead of one.
+    #[serde(default)]
+    all: bool,
+}
+
+/// The session's memory after a memory operation, so clients can mirror it.
+#[derive(Debug, Serialize, ToSchema)]
+struct MemoryResponse {
+    /// The main register `M`.
+    memory: f64,
+    /// The register the request addressed.
+    register: Register,
+    /// That register's value.
+    value: f64,
+    /// Every register by name, `M` and `M1` to `M9`.
+    registers: BTreeMap<String, f64>,
+}
+
+impl MemoryResponse {
+    fn new(calculator: &Calculator, register: Register) -> Self {
+        let memory = calculator.memory();
+        MemoryResponse {
+            memory: memory.get(Register::Main),
+            register,
+            value: memory.get(register),
+            registers: memory.iter().map(|(register, value)| (register.to_string(), value)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct VariablesResponse {
+    variables: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct VariableResponse {
+    name: String,
+    value: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct FunctionsResponse {
+    functions: Vec<FunctionResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct FunctionResponse {
+    name: String,
+    params: Vec<String>,
+    /// The definition, e.g. `f(x, y) = x^2 + 3*x*y`.
+    definition: String,
+}
+
+impl FunctionResponse {
+    fn new(name: &str, function: &UserFunction) -> Self {
+        FunctionResponse {
+            name: name.to_string(),
+            params: function.params.clone(),
+            definition: function.source.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct FitRequest {
+    /// `linear`, `polynomial`, `exponential`, `logarithmic` or `power`.
+    model: String,
+    /// Degree of a `polynomial` fit, 1 to 10.
+    degree: Option<usize>,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    /// Name of the function defined to predict from the fit; `predict` by default.
+    name: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct FitResponse {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    degree: Option<usize>,
+    /// `[a, b]` as in the model's formula, or `[c0, …, cn]` for polynomials.
+    coefficients: Vec<f64>,
+    r_squared: f64,
+    /// `y - prediction` for every point, in request order.
+    residuals: Vec<f64>,
+    /// The fitted curve, e.g. `1.5 + 2*x`.
+    equation: String,
+    /// The function defined for later expressions, e.g. `predict(x) = 1.5 + 2*x`.
+    function: FunctionResponse,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct HistoryResponse {
+    history: Vec<HistoryEntry>,
+}
+
+/// Filters for the history endpoints. Dates are `YYYY-MM-DD` in UTC and
+/// include the whole day.
+#[derive(Debug, Deserialize, IntoParams)]
+struct HistoryParams {
+    /// Case-insensitive text to find in the expression, operation or error.
+    q: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl HistoryParams {
+    fn query(&self) -> Result<HistoryQuery, CalcError> {
+        HistoryQuery::parse(self.q.as_deref(), self.from.as_deref(), self.to.as_deref())
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct ExportParams {
+    /// `json` (the default) or `csv`.
+    format: Option<String>,
+    q: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct OperationsResponse {
+    operations: Vec<OperationInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct OperationInfo {
+    name: String,
+    /// Number of operands, or the least number for variadic operations.
+    arity: usize,
+    /// Whether the last operand may repeat; pass the operands in `args`, or
+    /// in `values` for statistics.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    variadic: bool,
+    /// Human-readable operand names, in order.
+    operands: Vec<&'static str>,
+    /// Request fields the operands are read from.
+    fields: Vec<&'static str>,
+    symbol: String,
+    domain: Option<String>,
+    example: OperationExample,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct OperationExample {
+    #[schema(value_type = Object)]
+    request: serde_json::Value,
+    expression: String,
+    /// In degrees for angle operations; `None` if the example itself fails.
+    result: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct CalculationResponse {
+    /// Left out when an exact-mode result is beyond the range of an `f64`;
+    /// `exact` still holds it.
+    #[serde(skip_serializing_if = "beyond_f64")]
+    #[schema(required = false)]
+    result: f64,
+    expression: String,
+    success: bool,
+    error: Option<ErrorResponse>,
+    /// Decimal digits of an exact-mode result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact: Option<String>,
+    /// Imaginary part of a complex-mode result; `result` holds the real part.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imaginary: Option<f64>,
+    /// Argument of a polar result, in the request's angle unit; `result` then holds the magnitude.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    angle: Option<f64>,
+    /// The result in every base, for programmer operations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bases: Option<Bases>,
+    /// Name of the function a definition created; `result` is then 0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    defined: Option<String>,
+    /// Every result of a statistic that has several, such as Q1, Q2 and Q3
+    /// for `quartiles`; `result` holds the first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<Vec<f64>>,
+    /// Decimal digits of every result of `factorize` or `divisors`, which
+    /// `values` may round beyond 2^53.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact_values: Option<Vec<String>>,
+}
+
+impl CalculationResponse {
+    fn from_result(expression: String, result: Result<f64, CalcError>, field: Option<&str>) -> Self {
+        match result {
+            Ok(result) => CalculationResponse {
+                result,
+                expression,
+                success: true,
+                error: None,
+                exact: None,
+                imaginary: None,
+                angle: None,
+                bases: None,
+                defined: None,
+                values: None,
+                exact_values: None,
+            },
+            Err(e) => CalculationResponse {
+                result: 0.0,
+                expression,
+                success: false,
+                error: Some(ErrorResponse::new(&e, field)),
+                exact: None,
+                imaginary: None,
+                angle: None,
+                bases: None,
+                defined: None,
+                values: None,
+                exact_values: None,
+            },
+        }
+    }
+
+    fn from_exact(expression: String, result: Result<BigInt, CalcError>, field: Option<&str>) -> Self {
+        let exact = result.as_ref().ok().map(BigInt::to_string);
+        let mut response = Self::from_result(expression, result.map(|n| n.to_f64()), field);
+        response.exact = exact;
+        response
+    }
+
+    fn from_complex(expression: String, result: Result<Complex, CalcError>, field: Option<&str>) -> Self {
+        let imaginary = result.as_ref().ok().map(|z| z.im);
+        let mut response = Self::from_result(expression, result.map(|z| z.re), field);
+        response.imaginary = imaginary;
+        response
+    }
+}
+
+fn beyond_f64(result: &f64) -> bool {
+    !result.is_finite()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorResponse {
+    /// Stable machine-readable code such as `division_by_zero` or `unknown_operation`.
+    code: &'static str,
+    message: String,
+    /// The request field the error is about, when there is one.
+    field: Option<String>,
+}
+
+impl ErrorResponse {
+    fn new(error: &CalcError, field: Option<&str>) -> Self {
+        ErrorResponse {
+            code: error.code(),
+            message: error.to_string(),
+            field: error.field().or(field).map(str::to_string),
+        }
+    }
+}
+
+/// A `CalcError` returned from a handler, rendered as an `ErrorResponse`,
+/// with the request field it concerns when the error cannot say itself.
+struct ApiError(CalcError, Option<&'static str>);
+
+impl From<CalcError> for ApiError {
+    fn from(error: CalcError) -> Self {
+        ApiError(error, None)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            CalcError::BatchTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            CalcError::UnknownVariable(_) | CalcError::UnknownFunction(_) => StatusCode::NOT_FOUND,
+            ref e if e.is_request_error() => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, Json(ErrorResponse::new(&self.0, self.1))).into_response()
+    }
+}
+
+/// The session header every stateful endpoint accepts. Only used to
+/// describe the header; `session_middleware` does the reading.
+#[derive(IntoParams)]
+#[into_params(parameter_in = Header)]
+#[allow(dead_code)]
+struct SessionHeader {
+    /// Session token from an earlier response; the `calc_session` cookie works too.
+    #[param(rename = "x-session-id")]
+    session: Option<String>,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Calculator API", description = "Scientific calculator with per-session memory, history and settings."),
+    paths(
+        calculate,
+        calculate_batch,
+        operations,
+        evaluate,
+        fit,
+        get_settings,
+        update_settings,
+        memory_state,
+        memory_recall,
+        memory_store,
+        memory_add,
+        memory_subtract,
+        memory_clear,
+        history,
+        export_history,
+        clear_history,
+        list_variables,
+        get_variable,
+        delete_variable,
+        list_functions,
+        get_function,
+        delete_function,
+    ),
+    components(schemas(AngleUnit, HistoryEntry, Bases))
+)]
+struct ApiDoc;
+
+/// The OpenAPI document, with the `operation` field narrowed to the names
+/// in the registry and the statistics so generated clients get a proper enum.
+async fn openapi(State(state): State<AppState>) -> Json<utoipa::openapi::OpenApi> {
+    let mut doc = ApiDoc::openapi();
+    let names: Vec<String> = state
+        .registry
+        .iter()
+        .map(|operator| operator.name().to_string())
+        .chain(Statistic::ALL.iter().map(Statistic::to_string))
+        .collect();
+    let request = doc
+        .components
+        .as_mut()
+        .and_then(|components| components.schemas.get_mut("CalculationRequest"));
+    if let Some(RefOr::T(Schema::Object(request))) = request {
+        let operation = ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("Name of a registered operation or a statistic"))
+            .enum_values(Some(names));
+        request.properties.insert("operation".to_string(), operation.into());
+    }
+    Json(doc)
+}
+
+async fn index() -> Html<&'static str> {
+    Html(include_str!("../index.html"))
+}
+
+fn operand<'a>(operand: &'a Option<Operand>, field: &'static str) -> Result<&'a Operand, CalcError> {
+    operand.as_ref().ok_or(CalcError::MissingOperand(field))
+}
+
+fn binary_operands(request: &CalculationRequest) -> Result<(f64, f64), CalcError> {
+    let a = operand(&request.a, "a")?.to_f64("a")?;
+    let b = operand(&request.b, "b")?.to_f64("b")?;
+    Ok((a, b))
+}
+
+fn unary_operand(request: &CalculationRequest) -> Result<f64, CalcError> {
+    operand(&request.value, "value")?.to_f64("value")
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/calculate",
+    tag = "calculate",
+    request_body = CalculationRequest,
+    params(SessionHeader),
+    responses(
+        (status = 200, description = "Result of the operation; `success` is false when the maths fails", body = CalculationResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "The calculation failed", body = ErrorResponse),
+    )
+)]
+async fn calculate(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(request): Json<CalculationRequest>,
+) -> Result<Json<CalculationResponse>, ApiError> {
+    let response = state.with_calculator(&session, |calculator| dispatch(calculator, request))?;
+    Ok(Json(response))
+}
+
+/// Runs each request in order against the session's calculator. Items that
+/// fail, even ones that do not deserialize, become unsuccessful responses
+/// instead of failing the whole batch.
+#[utoipa::path(
+    post,
+    path = "/api/calculate/batch",
+    tag = "calculate",
+    request_body = Vec<CalculationRequest>,
+    params(SessionHeader),
+    responses(
+        (status = 200, description = "One response per request, in order", body = Vec<CalculationResponse>),
+        (status = 400, description = "The body is not a JSON array", body = ErrorResponse),
+        (status = 413, description = "More requests than the server's batch limit", body = ErrorResponse),
+    )
+)]
+async fn calculate_batch(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(requests): Json<Vec<serde_json::Value>>,
+) -> Result<Json<Vec<CalculationResponse>>, ApiError> {
+    if requests.len() > state.max_batch_size {
+        return Err(CalcError::BatchTooLarge {
+            size: requests.len(),
+            max: state.max_batch_size,
+        }
+        .into());
+    }
+
+    let responses = state.with_calculator(&session, |calculator| {
+        requests
+            .into_iter()
+            .map(|item| {
+                serde_json::from_value::<CalculationRequest>(item)
+                    .map_err(|e| CalcError::InvalidRequest(e.to_string()))
+                    .and_then(|request| dispatch(calculator, request))
+                    .unwrap_or_else(|e| CalculationResponse::from_result(String::new(), Err(e), None))
+            })
+            .collect()
+    });
+    Ok(Json(responses))
+}
+
+fn dispatch(calculator: &mut Calculator, request: CalculationRequest) -> Result<CalculationResponse, CalcError> {
+    // The registry refuses statistic names, so this shadows no operation
+    if let Ok(statistic) = request.operation.parse::<Statistic>() {
+        return dispatch_statistic(calculator, statistic, &request);
+    }
+
+    let operator = calculator
+        .registry()
+        .get(&request.operation)
+        .cloned()
+        .ok_or_else(|| CalcError::UnknownOperation(request.operation.clone()))?;
+
+    if let Ok(operation) = request.operation.parse::<BitOperation>() {
+        return dispatch_programmer(calculator, operation, &request);
+    }
+    if let Ok(operation) = request.operation.parse::<NumberOperation>() {
+        return dispatch_number_theory(calculator, operation, &request);
+    }
+
+    // Exact and complex modes only exist for the built-in operations
+    if let Ok(operation) = request.operation.parse::<Operation>() {
+        if request.exact {
+            if let Some(response) = dispatch_exact(calculator, operation, &request)? {
+                return Ok(response);
+            }
+        }
+        if request.complex || matches!(operation, Operation::ToPolar | Operation::FromPolar) {
+            if let Some(response) = dispatch_complex(calculator, operation, &request)? {
+                return Ok(response);
+            }
+        }
+    } else if request.complex && has_imaginary(&request) {
+        return Err(complex_unsupported(operator.name()));
+    }
+
+    let args = request_operands(&request, operator.as_ref())?;
+    let unit = request.angle_unit.unwrap_or(calculator.angle_unit());
+    let expression = operator.format(&args, unit);
+    let result = Registry::apply(operator.as_ref(), calculator, &args, unit);
+    let field = operator
+        .error_operand()
+        .map(|index| operand_field(&request, operator.arity(), index));
+
+    match &result {
+        Ok(value) => calculator.record_result(operator.name(), &expression, *value),
+        Err(e) => calculator.record_failure(operator.name(), &expression, e),
+    }
+    Ok(CalculationResponse::from_result(expression, result, field))
+}
+
+/// Operands in order: `value` for unary operations, `a` and `b` for binary
+/// ones, or `args` for any arity.
+fn request_operands(request: &CalculationRequest, operator: &dyn Operator) -> Result<Vec<f64>, CalcError> {
+    if let Some(args) = &request.args {
+        Registry::check_arity(operator, args.len())?;
+        return args.iter().map(|arg| arg.to_f64("args")).collect();
+    }
+    match operator.arity() {
+        0 => Ok(Vec::new()),
+        1 => Ok(vec![unary_operand(request)?]),
+        2 => {
+            let (a, b) = binary_operands(request)?;
+            Ok(vec![a, b])
+        }
+        _ => Err(CalcError::MissingOperand("args")),
+    }
+}
+
+/// The request field holding operand `index` of an operation of `arity`.
+fn operand_field(request: &CalculationRequest, arity: usize, index: usize) -> &'static str {
+    match operand_fields(arity).get(index) {
+        Some(field) if request.args.is_none() => field,
+        _ => "args",
+    }
+}
+
+/// The request fields an operation of `arity` reads when `args` is absent.
+fn operand_fields(arity: usize) -> &'static [&'static str] {
+    match arity {
+        0 => &[],
+        1 => &["value"],
+        2 => &["a", "b"],
+        _ => &["args"],
+    }
+}
+
+/// The session's word size with any overrides applied.
+fn request_word_size(calculator: &Calculator, bits: Option<u32>, signed: Option<bool>) -> Result<WordSize, CalcError> {
+    let current = calculator.word_size();
+    WordSize::new(bits.unwrap_or(current.bits()), signed.unwrap_or(current.signed()))
+}
+
+/// Integer path for programmer operations: operands may be written in any
+/// base, are wrapped to the word size, and the result comes back in every base.
+fn dispatch_programmer(
+    calculator: &mut Calculator,
+    operation: BitOperation,
+    request: &CalculationRequest,
+) -> Result<CalculationResponse, CalcError> {
+    let word = request_word_size(calculator, request.word_size, request.signed)?;
+    let arity = operation.arity();
+    let args = match &request.args {
+        Some(args) if args.len() != arity => {
+            return Err(CalcError::ArgumentCount {
+                function: operation.name().to_string(),
+                expected: arity,
+                found: args.len(),
+            })
+        }
+        Some(args) => args.iter().map(|arg| arg.to_word_integer("args")).collect::<Result<Vec<_>, _>>()?,
+        None if arity == 1 => vec![operand(&request.value, "value")?.to_word_integer("value")?],
+        None => vec![
+            operand(&request.a, "a")?.to_word_integer("a")?,
+            operand(&request.b, "b")?.to_word_integer("b")?,
+        ],
+    };
+
+    let operands: Vec<String> = args.iter().map(i128::to_string).collect();
+    let expression = format!("{} ({})", operation.describe(&operands), word);
+    let result = operation.apply(word, &args);
+    let field = (arity == 2).then(|| operand_field(request, arity, 1));
+
+    let bases = match result {
+        Ok(pattern) => Some(calculator.record_word_result(operation.name(), &expression, word, pattern)),
+        Err(ref e) => {
+            calculator.record_failure(operation.name(), &expression, e);
+            None
+        }
+    };
+    let mut response = CalculationResponse::from_result(expression, result.map(|pattern| word.value(pattern) as f64), field);
+    response.bases = bases;
+    Ok(response)
+}
+
+/// Integer path for number theory: operands may be written in any base and
+/// must fit in 64 bits, and results come back exactly in `exact`, or
+/// `exact_values` for lists.
+fn dispatch_number_theory(
+    calculator: &mut Calculator,
+    operation: NumberOperation,
+    request: &CalculationRequest,
+) -> Result<CalculationResponse, CalcError> {
+    let arity = operation.arity();
+    let args = match &request.args {
+        Some(args) => {
+            Registry::check_arity(&operation, args.len())?;
+            args.iter().map(|arg| arg.to_word_integer("args")).collect::<Result<Vec<_>, _>>()?
+        }
+        None if arity == 1 => vec![operand(&request.value, "value")?.to_word_integer("value")?],
+        None if arity == 2 => vec![
+            operand(&request.a, "a")?.to_word_integer("a")?,
+            operand(&request.b, "b")?.to_word_integer("b")?,
+        ],
+        None => return Err(CalcError::MissingOperand("args")),
+    };
+
+    let operands: Vec<String> = args.iter().map(i128::to_string).collect();
+    let expression = operation.describe(&operands);
+    let results = operation.apply(&args);
+    let field = operation
+        .error_operand()
+        .map(|index| operand_field(request, arity, index));
+
+    match &results {
+        Ok(results) => calculator.record_integers(operation, &expression, results),
+        Err(e) => calculator.record_failure(operation.name(), &expression, e),
+    }
+    let first = results.clone().map(|results| results.first().copied().unwrap_or_default());
+    let mut response = CalculationResponse::from_result(expression, first.map(|n| n as f64), field);
+    if let Ok(results) = results {
+        if operation.is_list() {
+            response.values = Some(results.iter().map(|&n| n as f64).collect());
+            response.exact_values = Some(results.iter().map(i128::to_string).collect());
+        } else {
+            response.exact = results.first().map(i128::to_string);
+        }
+    }
+    Ok(response)
+}
+
+/// Statistics take the whole `values` data set rather than scalar operands,
+/// so they sit outside the registry.
+fn dispatch_statistic(
+    calculator: &mut Calculator,
+    statistic: Statistic,
+    request: &CalculationRequest,
+) -> Result<CalculationResponse, CalcError> {
+    let values = request.values.as_deref().ok_or(CalcError::MissingOperand("values"))?;
+    let expression = statistic.describe(values, request.percentile);
+    let results = statistic.compute(values, request.percentile);
+
+    match &results {
+        Ok(results) => calculator.record_statistic(statistic.name(), &expression, results),
+        Err(e) => calculator.record_failure(statistic.name(), &expression, e),
+    }
+    let percentile_invalid = request.percentile.is_some_and(|p| !(0.0..=100.0).contains(&p));
+    let field = match results {
+        Err(CalcError::Domain(_)) if statistic == Statistic::Percentile && percentile_invalid => Some("percentile"),
+        Err(CalcError::Domain(_)) => Some("values"),
+        _ => None,
+    };
+    let mut response = CalculationResponse::from_result(expression, results.clone().map(|results| results[0]), field);
+    response.values = results.ok().filter(|results| results.len() > 1);
+    Ok(response)
+}
+
+fn has_imaginary(request: &CalculationRequest) -> bool {
+    [request.a_im, request.b_im, request.value_im]
+        .iter()
+        .any(|im| im.is_some_and(|im| im != 0.0))
+}
+
+fn complex_unsupported(operation: &str) -> CalcError {
+    CalcError::Domain(format!("Operation '{}' does not support complex operands", operation))
+}
+
+/// Big-integer path for `exact: true` requests. Returns `None` when the
+/// operation has no exact form or the operands are not integers, in which
+/// case the caller falls back to the `f64` dispatch.
+fn dispatch_exact(
+    calculator: &mut Calculator,
+    operation: Operation,
+    request: &CalculationRequest,
+) -> Result<Option<CalculationResponse>, CalcError> {
+    let (expression, result, field) = match operation {
+        op @ (Operation::Add | Operation::Subtract | Operation::Multiply | Operation::Power) => {
+            let a = operand(&request.a, "a")?.to_integer("a")?;
+            let b = operand(&request.b, "b")?.to_integer("b")?;
+            let (Some(a), Some(b)) = (a, b) else {
+                return Ok(None);
+            };
+            match op {
+                Operation::Add => (format!("{} + {}", a, b), Ok(calculator.exact_add(&a, &b)), None),
+                Operation::Subtract => (format!("{} - {}", a, b), Ok(calculator.exact_subtract(&a, &b)), None),
+                Operation::Multiply => (format!("{} × {}", a, b), Ok(calculator.exact_multiply(&a, &b)), None),
+                _ if b.is_negative() => return Ok(None),
+                _ => (format!("{} ^ {}", a, b), calculator.exact_power(&a, &b), Some("b")),
+            }
+        }
+        Operation::Square => {
+            let Some(value) = operand(&request.value, "value")?.to_integer("value")? else {
+                return Ok(None);
+            };
+            (format!("{}²", value), Ok(calculator.exact_multiply(&value, &value)), Some("value"))
+        }
+        Operation::Factorial => {
+            let Some(value) = operand(&request.value, "value")?.to_integer("value")? else {
+                return Ok(None);
+            };
+            (format!("{}!", value), calculator.exact_factorial(&value), Some("value"))
+        }
+        op @ (Operation::DoubleFactorial | Operation::Catalan) => {
+            let Some(value) = operand(&request.value, "value")?.to_integer("value")? else {
+                return Ok(None);
+            };
+            match op {
+                Operation::DoubleFactorial => {
+                    (format!("{}!!", value), calculator.exact_double_factorial(&value), Some("value"))
+                }
+                _ => (format!("catalan({})", value), calculator.exact_catalan(&value), Some("value")),
+            }
+        }
+        op @ (Operation::Combinations
+        | Operation::Permutations
+        | Operation::StirlingFirst
+        | Operation::StirlingSecond) => {
+            let a = operand(&request.a, "a")?.to_integer("a")?;
+            let b = operand(&request.b, "b")?.to_integer("b")?;
+            let (Some(a), Some(b)) = (a, b) else {
+                return Ok(None);
+            };
+            match op {
+                Operation::Combinations => (format!("C({}, {})", a, b), calculator.exact_combinations(&a, &b), None),
+                Operation::Permutations => (format!("P({}, {})", a, b), calculator.exact_permutations(&a, &b), None),
+                Operation::StirlingFirst => {
+                    (format!("stirling1({}, {})", a, b), calculator.exact_stirling_first(&a, &b), None)
+                }
+                _ => (format!("stirling2({}, {})", a, b), calculator.exact_stirling_second(&a, &b), None),
+            }
+        }
+        Operation::Multinomial => {
+            let counts = match &request.args {
+                Some(args) => args.iter().map(|arg| arg.to_integer("args")).collect::<Result<Option<Vec<_>>, _>>()?,
+                None => [(operand(&request.a, "a")?, "a"), (operand(&request.b, "b")?, "b")]
+                    .into_iter()
+                    .map(|(operand, field)| operand.to_integer(field))
+                    .collect::<Result<_, _>>()?,
+            };
+            let Some(counts) = counts else {
+                return Ok(None);
+            };
+            Registry::check_arity(&operation, counts.len())?;
+            let counts_text: Vec<String> = counts.iter().map(BigInt::to_string).collect();
+            let expression = format!("multinomial({})", counts_text.join(", "));
+            (expression, calculator.exact_multinomial(&counts), None)
+        }
+        _ => return Ok(None),
+    };
+
+    match &result {
+        Ok(value) => {
+            let approximation = Some(value.to_f64()).filter(|n| n.is_finite());
+            let entry = HistoryEntry::success(operation.name(), &expression, approximation);
+            calculator.add_to_history(entry.with_output(value.to_string()));
+            // `ans` is an `f64`, so results beyond its range leave it alone
+            if let Some(approximation) = approximation {
+                calculator.set_answer(approximation);
+            }
+        }
+        Err(e) => calculator.record_failure(operation.name(), &expression, e),
+    }
+    Ok(Some(CalculationResponse::from_exact(expression, result, field)))
+}
+
+fn complex_operand(
+    real: &Option<Operand>,
+    imaginary: Option<f64>,
+    field: &'static str,
+) -> Result<Complex, CalcError> {
+    let re = operand(real, field)?.to_f64(field)?;
+    Ok(Complex::new(re, imaginary.unwrap_or(0.0)))
+}
+
+fn complex_angle_label(z: Complex, unit: AngleUnit) -> String {
+    if z.im == 0.0 {
+        unit.label(z)
+    } else {
+        unit.label(format!("({})", z))
+    }
+}
+
+/// Complex-number path for `complex: true` requests and polar conversions.
+/// Returns `None` for operations without a complex form when every operand
+/// is real, so the caller falls back to the `f64` dispatch.
+fn dispatch_complex(
+    calculator: &mut Calculator,
+    operation: Operation,
+    request: &CalculationRequest,
+) -> Result<Option<CalculationResponse>, CalcError> {
+    let binary = || -> Result<(Complex, Complex), CalcError> {
+        Ok((
+            complex_operand(&request.a, request.a_im, "a")?,
+            complex_operand(&request.b, request.b_im, "b")?,
+        ))
+    };
+    let unary = || complex_operand(&request.value, request.value_im, "value");
+    let unit = request.angle_unit.unwrap_or(calculator.angle_unit());
+
+    let (expression, result, field) = match operation {
+        Operation::Add => {
+            let (a, b) = binary()?;
+            (format!("({}) + ({})", a, b), calculator.complex_add(a, b), None)
+        }
+        Operation::Subtract => {
+            let (a, b) = binary()?;
+            (format!("({}) - ({})", a, b), calculator.complex_subtract(a, b), None)
+        }
+        Operation::Multiply => {
+            let (a, b) = binary()?;
+            (format!("({}) × ({})", a, b), calculator.complex_multiply(a, b), None)
+        }
+        Operation::Divide => {
+            let (a, b) = binary()?;
+            (format!("({}) ÷ ({})", a, b), calculator.complex_divide(a, b), Some("b"))
+        }
+        Operation::Power => {
+            let (a, b) = binary()?;
+            (format!("({}) ^ ({})", a, b), calculator.complex_power(a, b), None)
+        }
+        Operation::Sqrt => {
+            let z = unary()?;
+            (format!("√({})", z), Ok(calculator.complex_sqrt(z)), Some("value"))
+        }
+        Operation::Square => {
+            let z = unary()?;
+            (format!("({})²", z), calculator.complex_multiply(z, z), Some("value"))
+        }
+        Operation::Reciprocal => {
+            let z = unary()?;
+            (format!("1/({})", z), calculator.complex_divide(Complex::from(1.0), z), Some("value"))
+        }
+        Operation::Ln => {
+            let z = unary()?;
+            (format!("ln({})", z), calculator.complex_ln(z), Some("value"))
+        }
+        Operation::Log10 => {
+            let z = unary()?;
+            (format!("log({})", z), calculator.complex_log10(z), Some("value"))
+        }
+        Operation::Sin => {
+            let angle = unary()?;
+            let z = angle.scale(unit.to_radians(1.0));
+            (format!("sin({})", complex_angle_label(angle, unit)), Ok(calculator.complex_sin(z)), Some("value"))
+        }
+        Operation::Cos => {
+            let angle = unary()?;
+            let z = angle.scale(unit.to_radians(1.0));
+            (format!("cos({})", complex_angle_label(angle, unit)), Ok(calculator.complex_cos(z)), Some("value"))
+        }
+        Operation::Tan => {
+            let angle = unary()?;
+            let z = angle.scale(unit.to_radians(1.0));
+            (format!("tan({})", complex_angle_label(angle, unit)), calculator.complex_tan(z), Some("value"))
+        }
+        Operation::Abs => {
+            let z = unary()?;
+            (format!("|{}|", z), Ok(Complex::from(z.abs())), Some("value"))
+        }
+        Operation::ToPolar => {
+            let z = unary()?;
+            let magnitude = z.abs();
+            let angle = unit.radians_to_unit(z.arg());
+            let expression = format!("{} → polar", z);
+            let entry = HistoryEntry::success(operation.name(), &expression, Some(magnitude));
+            calculator.add_to_history(entry.with_output(format!("{}∠{}", magnitude, unit.label(angle))));
+            calculator.set_answer(magnitude);
+            let mut response = CalculationResponse::from_result(expression, Ok(magnitude), None);
+            response.angle = Some(angle);
+            return Ok(Some(response));
+        }
+        Operation::FromPolar => {
+            let magnitude = operand(&request.a, "a")?.to_f64("a")?;
+            let angle = operand(&request.b, "b")?.to_f64("b")?;
+            let z = Complex::new(
+                magnitude * calculator.cos(angle, unit),
+                magnitude * calculator.sin(angle, unit),
+            );
+            (format!("{}∠{} → rectangular", magnitude, unit.label(angle)), Ok(z), None)
+        }
+        _ if has_imaginary(request) => return Err(complex_unsupported(operation.name())),
+        _ => return Ok(None),
+    };
+
+    match &result {
+        Ok(value) => {
+            let entry = HistoryEntry::success(operation.name(), &expression, Some(value.re));
+            calculator.add_to_history(entry.with_output(value.to_string()));
+            // `ans` is real, so only real results replace it
+            if value.im == 0.0 {
+                calculator.set_answer(value.re);
+            }
+        }
+        Err(e) => calculator.record_failure(operation.name(), &expression, e),
+    }
+    Ok(Some(CalculationResponse::from_complex(expression, result, field)))
+}
+
+/// Describes every registered operation, custom ones included, and every
+/// statistic, so clients can build their forms and keypads without
+/// hard-coding the list.
+#[utoipa::path(
+    get,
+    path = "/api/operations",
+    tag = "calculate",
+    responses((status = 200, description = "Every registered operation", body = OperationsResponse))
+)]
+async fn operations(State(state): State<AppState>) -> Json<OperationsResponse> {
+    let calculator = Calculator::with_registry(state.registry.clone());
+    let unit = AngleUnit::default();
+    let operations = calculator
+        .registry()
+        .iter()
+        .map(|operator| {
+            let args = operator.example();
+            let fields = operand_fields(operator.arity());
+
+            let mut request = serde_json::Map::new();
+            request.insert("operation".to_string(), operator.name().into());
+            if args.len() > fields.len() {
+                request.insert("args".to_string(), args.clone().into());
+            } else {
+                for (field, value) in fields.iter().zip(&args) {
+                    request.insert(field.to_string(), (*value).into());
+                }
+            }
+
+            OperationInfo {
+                name: operator.name().to_string(),
+                arity: operator.arity(),
+                variadic: operator.variadic(),
+                operands: operator.operands().to_vec(),
+                fields: fields.to_vec(),
+                symbol: operator.symbol().to_string(),
+                domain: operator.domain().map(str::to_string),
+                example: OperationExample {
+                    request: request.into(),
+                    expression: operator.format(&args, unit),
+                    result: Registry::apply(operator.as_ref(), &calculator, &args, unit).ok(),
+                },
+            }
+        })
+        .chain(Statistic::ALL.iter().map(|&statistic| statistic_info(statistic)))
+        .collect();
+    Json(OperationsResponse { operations })
+}
+
+/// Describes a statistic in the same shape as an operation. It takes the
+/// whole `values` data set, so it is listed as variadic.
+fn statistic_info(statistic: Statistic) -> OperationInfo {
+    let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+    let percentile = (statistic == Statistic::Percentile).then_some(90.0);
+
+    let mut request = serde_json::Map::new();
+    request.insert("operation".to_string(), statistic.name().into());
+    request.insert("values".to_string(), values.to_vec().into());
+    let (operands, fields, domain) = match percentile {
+        Some(p) => {
+            request.insert("percentile".to_string(), p.into());
+            (vec!["data set", "percentile"], vec!["values", "percentile"], Some("0 ≤ percentile ≤ 100".to_string()))
+        }
+        None => (vec!["data set"], vec!["values"], None),
+    };
+
+    OperationInfo {
+        name: statistic.name().to_string(),
+        arity: 1,
+        variadic: true,
+        operands,
+        fields,
+        symbol: statistic.name().to_string(),
+        domain,
+        example: OperationExample {
+            request: request.into(),
+            expression: statistic.describe(&values, percentile),
+            result: statistic.compute(&values, percentile).ok().map(|results| results[0]),
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/evaluate",
+    tag = "calculate",
+    request_body = EvaluateRequest,
+    params(SessionHeader),
+    responses(
+        (status = 200, description = "Value of the expression, or the function it defined", body = CalculationResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "The calculation failed", body = ErrorResponse),
+    )
+)]
+async fn evaluate(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(request): Json<EvaluateRequest>,
+) -> Result<Json<CalculationResponse>, ApiError> {
+    let statement = expr::parse_statement(&request.expression)?;
+    let expression = request.expression.trim().to_string();
+
+    let response = state.with_calculator(&session, |calculator| {
+        let unit = request.angle_unit.unwrap_or(calculator.angle_unit());
+        match statement.execute(calculator, unit) {
+            Ok(Some(value)) => {
+                calculator.record_result("evaluate", &expression, value);
+                CalculationResponse::from_result(expression, Ok(value), Some("expression"))
+            }
+            Ok(None) => {
+                let mut response = CalculationResponse::from_result(expression, Ok(0.0), Some("expression"));
+                if let Statement::Definition { name, .. } = &statement {
+                    response.defined = Some(name.clone());
+                }
+                response
+            }
+            Err(e) => {
+                calculator.record_failure("evaluate", &expression, &e);
+                CalculationResponse::from_result(expression, Err(e), Some("expression"))
+            }
+        }
+    });
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/fit",
+    tag = "calculate",
+    request_body = FitRequest,
+    params(SessionHeader),
+    responses(
+        (status = 200, description = "The fitted curve; its function is now defined in the session", body = FitResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "The data cannot be fitted", body = ErrorResponse),
+    )
+)]
+async fn fit(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(request): Json<FitRequest>,
+) -> Result<Json<FitResponse>, ApiError> {
+    let model = Model::new(&request.model, request.degree)?;
+    let name = request.name.as_deref().unwrap_or(analysis::DEFAULT_FUNCTION);
+    if !expr::is_identifier(name) {
+        let error = CalcError::InvalidRequest(format!("'{}' is not a valid function name", name));
+        return Err(ApiError(error, Some("name")));
+    }
+    let fit = analysis::fit(model, &request.x, &request.y)?;
+    let function = fit.function(name)?;
+    state
+        .with_calculator(&session, |calculator| calculator.define_function(name, function.clone()))
+        .map_err(|e| ApiError(e, Some("name")))?;
+
+    Ok(Json(FitResponse {
+        model: model.name().to_string(),
+        degree: match model {
+            Model::Polynomial(degree) => Some(degree),
+            _ => None,
+        },
+        equation: fit.equation(),
+        function: FunctionResponse::new(name, &function),
+        coefficients: fit.coefficients,
+        r_squared: fit.r_squared,
+        residuals: fit.residuals,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/settings",
+    tag = "session",
+    params(SessionHeader),
+    responses((status = 200, description = "The session's settings", body = SettingsPayload))
+)]
+async fn get_settings(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+) -> Json<SettingsPayload> {
+    Json(state.with_calculator(&session, |calculator| SettingsPayload::new(calculator)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/settings",
+    tag = "session",
+    request_body = SettingsUpdate,
+    params(SessionHeader),
+    responses(
+        (status = 200, description = "The updated settings", body = SettingsPayload),
+        (status = 400, description = "Unsupported word size", body = ErrorResponse),
+    )
+)]
+async fn update_settings(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(settings): Json<SettingsUpdate>,
+) -> Result<Json<SettingsPayload>, ApiError> {
+    let payload = state.with_calculator(&session, |calculator| {
+        let word_size = request_word_size(calculator, settings.word_size, settings.signed)?;
+        calculator.set_word_size(word_size);
+        if let Some(angle_unit) = settings.angle_unit {
+            calculator.set_angle_unit(angle_unit);
+        }
+        Ok::<_, CalcError>(SettingsPayload::new(calculator))
+    })?;
+    Ok(Json(payload))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/memory",
+    tag = "memory",
+    params(SessionHeader),
+    responses((status = 200, description = "Every memory register", body = MemoryResponse))
+)]
+async fn memory_state(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+) -> Json<MemoryResponse> {
+    Json(state.with_calculator(&session, |calculator| MemoryResponse::new(calculator, Register::Main)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/memory/recall",
+    tag = "memory",
+    request_body = RegisterRequest,
+    params(SessionHeader),
+    responses((status = 200, description = "MR: the register's value, which also becomes `ans`", body = MemoryResponse))
+)]
+async fn memory_recall(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    request: Option<Json<RegisterRequest>>,
+) -> Json<MemoryResponse> {
+    let register = request.and_then(|Json(request)| request.register).unwrap_or_default();
+    Json(state.with_calculator(&session, |calculator| {
+        calculator.set_answer(calculator.recall_memory(register));
+        MemoryResponse::new(calculator, register)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/memory/store",
+    tag = "memory",
+    request_body = MemoryRequest,
+    params(SessionHeader),
+    responses((status = 200, description = "MS: memory after storing the value", body = MemoryResponse))
+)]
+async fn memory_store(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(request): Json<MemoryRequest>,
+) -> Json<MemoryResponse> {
+    let register = request.register.unwrap_or_default();
+    Json(state.with_calculator(&session, |calculator| {
+        calculator.store_memory(register, request.value);
+        MemoryResponse::new(calculator, register)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/memory/add",
+    tag = "memory",
+    request_body = MemoryRequest,
+    params(SessionHeader),
+    responses((status = 200, description = "M+: memory after adding the value", body = MemoryResponse))
+)]
+async fn memory_add(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(request): Json<MemoryRequest>,
+) -> Json<MemoryResponse> {
+    let register = request.register.unwrap_or_default();
+    Json(state.with_calculator(&session, |calculator| {
+        calculator.add_to_memory(register, request.value);
+        MemoryResponse::new(calculator, register)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/memory/subtract",
+    tag = "memory",
+    request_body = MemoryRequest,
+    params(SessionHeader),
+    responses((status = 200, description = "M-: memory after subtracting the value", body = MemoryResponse))
+)]
+async fn memory_subtract(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Json(request): Json<MemoryRequest>,
+) -> Json<MemoryResponse> {
+    let register = request.register.unwrap_or_default();
+    Json(state.with_calculator(&session, |calculator| {
+        calculator.subtract_from_memory(register, request.value);
+        MemoryResponse::new(calculator, register)
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/memory/clear",
+    tag = "memory",
+    request_body = ClearMemoryRequest,
+    params(SessionHeader),
+    responses((status = 200, description = "MC: memory after clearing", body = MemoryResponse))
+)]
+async fn memory_clear(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    request: Option<Json<ClearMemoryRequest>>,
+) -> Json<MemoryResponse> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let register = request.register.unwrap_or_default();
+    Json(state.with_calculator(&session, |calculator| {
+        if request.all {
+            calculator.clear_all_memory();
+        } else {
+            calculator.clear_memory(register);
+        }
+        MemoryResponse::new(calculator, register)
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/history",
+    tag = "session",
+    params(SessionHeader, HistoryParams),
+    responses(
+        (status = 200, description = "Matching calculations, oldest first", body = HistoryResponse),
+        (status = 400, description = "Malformed date", body = ErrorResponse),
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Query(params): Query<HistoryParams>,
+) -> Result<Json<HistoryResponse>, ApiError> {
+    let query = params.query()?;
+    let history = state.with_calculator(&session, |calculator| {
+        calculator.history().search(&query).into_iter().cloned().collect()
+    });
+    Ok(Json(HistoryResponse { history }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/history/export",
+    tag = "session",
+    params(SessionHeader, ExportParams),
+    responses(
+        (status = 200, description = "Matching calculations as a JSON array or CSV file", content(
+            (Vec<HistoryEntry> = "application/json"),
+            (String = "text/csv"),
+        )),
+        (status = 400, description = "Unknown format or malformed date", body = ErrorResponse),
+    )
+)]
+async fn export_history(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, ApiError> {
+    let format: ExportFormat = params.format.as_deref().map(str::parse).transpose()?.unwrap_or_default();
+    let query = HistoryQuery::parse(params.q.as_deref(), params.from.as_deref(), params.to.as_deref())?;
+    let body = state.with_calculator(&session, |calculator| {
+        history::export(&calculator.history().search(&query), format)
+    });
+    let disposition = format!("attachment; filename=\"history.{}\"", format.extension());
+    Ok(([(CONTENT_TYPE, format.content_type().to_string()), (CONTENT_DISPOSITION, disposition)], body).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/history",
+    tag = "session",
+    params(SessionHeader),
+    responses((status = 200, description = "The now empty history", body = HistoryResponse))
+)]
+async fn clear_history(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+) -> Json<HistoryResponse> {
+    state.clear_history(&session);
+    Json(HistoryResponse { history: Vec::new() })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/variables",
+    tag = "variables",
+    params(SessionHeader),
+    responses((status = 200, description = "Every variable in the session, `ans` included", body = VariablesResponse))
+)]
+async fn list_variables(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+) -> Json<VariablesResponse> {
+    let variables = state.with_calculator(&session, |calculator| calculator.variables().clone());
+    Json(VariablesResponse { variables })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/variables/{name}",
+    tag = "variables",
+    params(SessionHeader, ("name" = String, Path, description = "Variable name")),
+    responses(
+        (status = 200, description = "The variable's value", body = VariableResponse),
+        (status = 404, description = "No such variable", body = ErrorResponse),
+    )
+)]
+async fn get_variable(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Path(name): Path<String>,
+) -> Result<Json<VariableResponse>, ApiError> {
+    let value = state
+        .with_calculator(&session, |calculator| calculator.variable(&name))
+        .ok_or_else(|| CalcError::UnknownVariable(name.clone()))?;
+    Ok(Json(VariableResponse { name, value }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/variables/{name}",
+    tag = "variables",
+    params(SessionHeader, ("name" = String, Path, description = "Variable name")),
+    responses(
+        (status = 200, description = "The variable's last value", body = VariableResponse),
+        (status = 404, description = "No such variable", body = ErrorResponse),
+    )
+)]
+async fn delete_variable(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Path(name): Path<String>,
+) -> Result<Json<VariableResponse>, ApiError> {
+    let value = state
+        .with_calculator(&session, |calculator| calculator.remove_variable(&name))
+        .ok_or_else(|| CalcError::UnknownVariable(name.clone()))?;
+    Ok(Json(VariableResponse { name, value }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/functions",
+    tag = "variables",
+    params(SessionHeader),
+    responses((status = 200, description = "Every function defined in the session", body = FunctionsResponse))
+)]
+async fn list_functions(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+) -> Json<FunctionsResponse> {
+    let functions = state.with_calculator(&session, |calculator| {
+        calculator
+            .functions()
+            .iter()
+            .map(|(name, function)| FunctionResponse::new(name, function))
+            .collect()
+    });
+    Json(FunctionsResponse { functions })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/functions/{name}",
+    tag = "variables",
+    params(SessionHeader, ("name" = String, Path, description = "Function name")),
+    responses(
+        (status = 200, description = "The function's definition", body = FunctionResponse),
+        (status = 404, description = "No such function", body = ErrorResponse),
+    )
+)]
+async fn get_function(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Path(name): Path<String>,
+) -> Result<Json<FunctionResponse>, ApiError> {
+    let function = state
+        .with_calculator(&session, |calculator| calculator.function(&name).cloned())
+        .ok_or_else(|| CalcError::UnknownFunction(name.clone()))?;
+    Ok(Json(FunctionResponse::new(&name, &function)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/functions/{name}",
+    tag = "variables",
+    params(SessionHeader, ("name" = String, Path, description = "Function name")),
+    responses(
+        (status = 200, description = "The removed definition", body = FunctionResponse),
+        (status = 404, description = "No such function", body = ErrorResponse),
+    )
+)]
+async fn delete_function(
+    State(state): State<AppState>,
+    Extension(session): Extension<SessionId>,
+    Path(name): Path<String>,
+) -> Result<Json<FunctionResponse>, ApiError> {
+    let function = state
+        .with_calculator(&session, |calculator| calculator.remove_function(&name))
+        .ok_or_else(|| CalcError::UnknownFunction(name.clone()))?;
+    Ok(Json(FunctionResponse::new(&name, &function)))
+}
+
+const DEFAULT_MAX_BATCH_SIZE: usize = 1000;
+
+pub async fn serve(host: String, port: u16, registry: Arc<Registry>, history_options: HistoryOptions) -> std::io::Result<()> {
+    // Configure CORS
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([CONTENT_TYPE, HeaderName::from_static(session::SESSION_HEADER)])
+        .expose_headers([HeaderName::from_static(session::SESSION_HEADER)])
+        .allow_origin(Any);
+
+    let max_batch_size = std::env::var("CALC_MAX_BATCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_MAX_BATCH_SIZE);
+
+    // Build our application with a route
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/api/calculate", post(calculate))
+        .route("/api/calculate/batch", post(calculate_batch))
+        .route("/api/operations", get(operations))
+        .route("/api/openapi.json", get(openapi))
+        .route("/api/evaluate", post(evaluate))
+        .route("/api/fit", post(fit))
+        .route("/api/memory", get(memory_state))
+        .route("/api/memory/recall", post(memory_recall))
+        .route("/api/memory/store", post(memory_store))
+        .route("/api/memory/add", post(memory_add))
+        .route("/api/memory/subtract", post(memory_subtract))
+        .route("/api/memory/clear", post(memory_clear))
+        .route("/api/history", get(history).delete(clear_history))
+        .route("/api/history/export", get(export_history))
+        .route("/api/variables", get(list_variables))
+        .route("/api/variables/{name}", get(get_variable).delete(delete_variable))
+        .route("/api/functions", get(list_functions))
+        .route("/api/functions/{name}", get(get_function).delete(delete_function))
+        .route("/api/settings", get(get_settings).put(update_settings))
+        .nest_service("/static", ServeDir::new("static"))
+        .layer(middleware::from_fn(session::session_middleware))
+        .layer(cors)
+        .with_state(
+            AppState::new(registry, max_batch_size)
+                .with_history(
+                    history_options.capacity,
+                    history_options.file.map(|path| HistoryLog::new(path, history_options.capacity)),
+                ),
+        );
+
+    // Run it
+    let listener = tokio::net::TcpListener::bind((host.as_str(), port)).await?;
+    let addr = listener.local_addr()?;
+    println!("🚀 Calculator server starting on http://{}", addr);
+    println!("📱 Open your browser and navigate to http://{}", addr);
+
+    axum::serve(listener, app).await
+}
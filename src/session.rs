@@ -0,0 +1,206 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use calculator::{history, Calculator, Registry};
+
+use crate::history_log::HistoryLog;
+
+pub const SESSION_HEADER: &str = "x-session-id";
+const SESSION_COOKIE: &str = "calc_session";
+
+/// Sessions unused for this long are dropped when new ones arrive.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// Most sessions held at once; beyond this the least recently used goes.
+const MAX_SESSIONS: usize = 10_000;
+
+/// A session's calculator, created on first use under the session's own lock.
+type SessionCell = Arc<Mutex<Option<Calculator>>>;
+
+struct Session {
+    calculator: SessionCell,
+    last_used: Instant,
+}
+
+/// Shared router state: one `Calculator` per session token, the operations
+/// they all share, where history is persisted, plus server limits.
+///
+/// Idle sessions expire and the number held is capped, so clients that never
+/// send their token back cannot exhaust memory. A dropped session loses its
+/// memory and variables; its history comes back from the history log.
+#[derive(Clone)]
+pub struct AppState {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    pub registry: Arc<Registry>,
+    pub max_batch_size: usize,
+    history_capacity: usize,
+    history_log: Option<Arc<HistoryLog>>,
+}
+
+impl AppState {
+    pub fn new(registry: Arc<Registry>, max_batch_size: usize) -> Self {
+        AppState {
+            sessions: Arc::default(),
+            registry,
+            max_batch_size,
+            history_capacity: history::DEFAULT_CAPACITY,
+            history_log: None,
+        }
+    }
+
+    /// Keeps `capacity` history entries per session and, given a log,
+    /// persists them there and restores them when a session is first used.
+    pub fn with_history(mut self, capacity: usize, log: Option<HistoryLog>) -> Self {
+        self.history_capacity = capacity;
+        self.history_log = log.map(Arc::new);
+        self
+    }
+
+    /// Runs `f` against the session's calculator, creating it on first use.
+    /// History entries `f` records are appended to the history log. Only
+    /// this session is locked while `f` runs.
+    pub fn with_calculator<T>(&self, session: &SessionId, f: impl FnOnce(&mut Calculator) -> T) -> T {
+        let cell = self.session_cell(session);
+        let mut cell = cell.lock().unwrap_or_else(PoisonError::into_inner);
+        let calculator = cell.get_or_insert_with(|| self.new_calculator(session));
+
+        let result = f(calculator);
+        if let Some(log) = &self.history_log {
+            if let Err(e) = log.append(&session.0, &calculator.take_unsaved_history()) {
+                eprintln!("Failed to save history: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Clears the session's history, in the log as well as in memory. The
+    /// marker is written under the session's lock, so no entry recorded
+    /// after the clear can land in the log before it.
+    pub fn clear_history(&self, session: &SessionId) {
+        self.with_calculator(session, |calculator| {
+            calculator.clear_history();
+            if let Some(log) = &self.history_log {
+                if let Err(e) = log.record_clear(&session.0) {
+                    eprintln!("Failed to save history: {}", e);
+                }
+            }
+        });
+    }
+
+    /// The session's entry, added if it is new. Adding one first drops
+    /// expired sessions and, at the cap, the least recently used.
+    fn session_cell(&self, session: &SessionId) -> SessionCell {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        if let Some(existing) = sessions.get_mut(&session.0) {
+            existing.last_used = now;
+            return existing.calculator.clone();
+        }
+
+        sessions.retain(|_, session| now.duration_since(session.last_used) < SESSION_IDLE_TIMEOUT);
+        if sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+        let calculator = SessionCell::default();
+        sessions.insert(session.0.clone(), Session { calculator: calculator.clone(), last_used: now });
+        calculator
+    }
+
+    fn new_calculator(&self, session: &SessionId) -> Calculator {
+        let mut calculator = Calculator::with_registry(self.registry.clone());
+        calculator.set_history_capacity(self.history_capacity);
+        if let Some(log) = &self.history_log {
+            match log.load(&session.0) {
+                Ok(entries) => entries.into_iter().for_each(|entry| calculator.add_to_history(entry)),
+                Err(e) => eprintln!("Failed to load history: {}", e),
+            }
+            calculator.track_unsaved_history();
+        }
+        calculator
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionId(pub String);
+
+/// Resolves the session from the `x-session-id` header or the `calc_session`
+/// cookie, minting a new one when neither is present. The token is echoed in
+/// the response header so non-browser clients can pick it up and share it.
+pub async fn session_middleware(mut request: Request, next: Next) -> Response {
+    let existing = session_from_headers(request.headers());
+    let id = existing.clone().unwrap_or_else(generate_session_id);
+    request.extensions_mut().insert(SessionId(id.clone()));
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    if existing.is_none() {
+        let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Lax", SESSION_COOKIE, id);
+        if let Ok(value) = HeaderValue::from_str(&cookie) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    response
+}
+
+fn session_from_headers(headers: &HeaderMap) -> Option<String> {
+    let from_header = headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let from_cookie = || {
+        headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == SESSION_COOKIE)
+            .map(|(_, value)| value.to_string())
+    };
+
+    from_header.or_else(from_cookie).filter(|id| is_valid_session_id(id))
+}
+
+fn is_valid_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Unguessable enough for sharing calculator state; not an authentication token.
+fn generate_session_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut halves = [0u64; 2];
+    for half in halves.iter_mut() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(nanos);
+        hasher.write_u64(count);
+        *half = hasher.finish();
+    }
+    format!("{:016x}{:016x}", halves[0], halves[1])
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::CalcError;
+
+/// Data sets longer than this are summarised as a count in expressions.
+const MAX_LISTED_VALUES: usize = 8;
+
+/// A descriptive statistic over a data set, requested by name like an
+/// [`Operation`](crate::Operation) but taking any number of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Statistic {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Mean,
+    Median,
+    Mode,
+    PopulationVariance,
+    SampleVariance,
+    PopulationStdDev,
+    SampleStdDev,
+    Quartiles,
+    Percentile,
+    Skewness,
+    Kurtosis,
+}
+
+impl Statistic {
+    pub const ALL: [Statistic; 15] = [
+        Statistic::Count,
+        Statistic::Sum,
+        Statistic::Min,
+        Statistic::Max,
+        Statistic::Mean,
+        Statistic::Median,
+        Statistic::Mode,
+        Statistic::PopulationVariance,
+        Statistic::SampleVariance,
+        Statistic::PopulationStdDev,
+        Statistic::SampleStdDev,
+        Statistic::Quartiles,
+        Statistic::Percentile,
+        Statistic::Skewness,
+        Statistic::Kurtosis,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Statistic::Count => "count",
+            Statistic::Sum => "sum",
+            Statistic::Min => "min",
+            Statistic::Max => "max",
+            Statistic::Mean => "mean",
+            Statistic::Median => "median",
+            Statistic::Mode => "mode",
+            Statistic::PopulationVariance => "population_variance",
+            Statistic::SampleVariance => "sample_variance",
+            Statistic::PopulationStdDev => "population_stddev",
+            Statistic::SampleStdDev => "sample_stddev",
+            Statistic::Quartiles => "quartiles",
+            Statistic::Percentile => "percentile",
+            Statistic::Skewness => "skewness",
+            Statistic::Kurtosis => "kurtosis",
+        }
+    }
+
+    /// Computes the statistic. Most statistics have one result; `quartiles`
+    /// returns Q1, Q2 and Q3, and `mode` every most frequent value in
+    /// ascending order. `percentile` (0 to 100) is only used by
+    /// [`Statistic::Percentile`].
+    ///
+    /// Percentiles and quartiles interpolate linearly between the closest
+    /// ranks, as spreadsheet `PERCENTILE.INC` does. Skewness and kurtosis
+    /// are the population moment coefficients; kurtosis is the excess over
+    /// a normal distribution.
+    pub fn compute(self, values: &[f64], percentile: Option<f64>) -> Result<Vec<f64>, CalcError> {
+        if values.iter().any(|value| !value.is_finite()) {
+            return Err(CalcError::InvalidOperand("values"));
+        }
+        if values.is_empty() && self != Statistic::Count && self != Statistic::Sum {
+            return Err(CalcError::Domain(format!("'{}' needs at least one value", self.name())));
+        }
+        let n = values.len() as f64;
+        let sum = || values.iter().fold(0.0, |sum, value| sum + value);
+        let mean = || sum() / n;
+        // Central moment of the given order
+        let moment = |order: i32| {
+            let mean = mean();
+            values.iter().map(|value| (value - mean).powi(order)).sum::<f64>() / n
+        };
+        let sample = |statistic: Statistic| {
+            if values.len() < 2 {
+                Err(CalcError::Domain(format!("'{}' needs at least two values", statistic.name())))
+            } else {
+                Ok(moment(2) * n / (n - 1.0))
+            }
+        };
+        let spread = |statistic: Statistic| {
+            let variance = moment(2);
+            if variance == 0.0 {
+                Err(CalcError::Domain(format!("'{}' is undefined when every value is equal", statistic.name())))
+            } else {
+                Ok(variance)
+            }
+        };
+
+        let result = match self {
+            Statistic::Count => n,
+            Statistic::Sum => sum(),
+            Statistic::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Statistic::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Statistic::Mean => mean(),
+            Statistic::Median => percentile_of(&sorted(values), 50.0),
+            Statistic::Mode => return Ok(modes(values)),
+            Statistic::PopulationVariance => moment(2),
+            Statistic::SampleVariance => sample(self)?,
+            Statistic::PopulationStdDev => moment(2).sqrt(),
+            Statistic::SampleStdDev => sample(self)?.sqrt(),
+            Statistic::Quartiles => {
+                let sorted = sorted(values);
+                return Ok([25.0, 50.0, 75.0].map(|p| percentile_of(&sorted, p)).to_vec());
+            }
+            Statistic::Percentile => {
+                let p = percentile.ok_or(CalcError::MissingOperand("percentile"))?;
+                if !(0.0..=100.0).contains(&p) {
+                    return Err(CalcError::Domain("Percentile must be between 0 and 100".to_string()));
+                }
+                percentile_of(&sorted(values), p)
+            }
+            Statistic::Skewness => moment(3) / spread(self)?.powf(1.5),
+            Statistic::Kurtosis => moment(4) / spread(self)?.powi(2) - 3.0,
+        };
+        if result.is_finite() {
+            Ok(vec![result])
+        } else {
+            Err(CalcError::Overflow(format!("'{}' overflowed", self.name())))
+        }
+    }
+
+    /// How the statistic is written in history and responses, e.g.
+    /// `mean(1, 2, 3)`; long data sets are shown by their size.
+    pub fn describe(self, values: &[f64], percentile: Option<f64>) -> String {
+        let data = if values.len() > MAX_LISTED_VALUES {
+            format!("{} values", values.len())
+        } else {
+            values.iter().map(f64::to_string).collect::<Vec<_>>().join(", ")
+        };
+        match (self, percentile) {
+            (Statistic::Percentile, Some(p)) => format!("percentile({}; {})", p, data),
+            _ => format!("{}({})", self.name(), data),
+        }
+    }
+}
+
+fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+/// The `p`th percentile of sorted, non-empty data, interpolating linearly.
+fn percentile_of(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn modes(values: &[f64]) -> Vec<f64> {
+    let sorted = sorted(values);
+    let runs: Vec<&[f64]> = sorted.chunk_by(|a, b| a == b).collect();
+    let longest = runs.iter().map(|run| run.len()).max().unwrap_or_default();
+    runs.into_iter().filter(|run| run.len() == longest).map(|run| run[0]).collect()
+}
+
+impl fmt::Display for Statistic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Statistic {
+    type Err = CalcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Statistic::ALL
+            .into_iter()
+            .find(|statistic| statistic.name() == s)
+            .ok_or_else(|| CalcError::UnknownOperation(s.to_string()))
+    }
+}
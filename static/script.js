@@ -5,6 +5,7 @@ let calculator = {
     operation: null,
     waitingForOperand: false,
     memory: 0,
+    registers: {},
     history: [],
     currentMode: 'basic'
 };
@@ -14,6 +15,7 @@ const mainDisplay = document.getElementById('mainDisplay');
 const historyDisplay = document.getElementById('historyDisplay');
 const memoryIndicator = document.getElementById('memoryIndicator');
 const memoryValue = document.getElementById('memoryValue');
+const memoryRegister = document.getElementById('memoryRegister');
 const historyList = document.getElementById('historyList');
 
 // API base URL
@@ -24,6 +26,7 @@ document.addEventListener('DOMContentLoaded', function() {
     updateDisplay();
     setupModeSwitching();
     setupKeyboardSupport();
+    callMemoryAPI('');
 });
 
 // Mode switching
@@ -109,13 +112,11 @@ function updateHistoryDisplay() {
 }
 
 function updateMemoryIndicator() {
-    if (calculator.memory !== 0) {
-        memoryIndicator.textContent = 'M';
-        memoryValue.textContent = formatNumber(calculator.memory.toString());
-    } else {
-        memoryIndicator.textContent = '';
-        memoryValue.textContent = '0';
-    }
+    // Name every register holding a value, e.g. "M M3"
+    const used = Object.keys(calculator.registers).filter(name => calculator.registers[name] !== 0);
+    memoryIndicator.textContent = used.join(' ');
+    const selected = calculator.registers[memoryRegister.value] || 0;
+    memoryValue.textContent = formatNumber(selected.toString());
 }
 
 function updateHistoryList() {
@@ -211,17 +212,17 @@ async function callCalculatorAPI(operation, a, b, value) {
             body: JSON.stringify(requestBody)
         });
 
+        const data = await response.json();
         if (!response.ok) {
-            throw new Error(`HTTP error! status: ${response.status}`);
+            // Request errors come back as a bare ErrorResponse body
+            return { success: false, error: data, result: 0 };
         }
-
-        const data = await response.json();
         return data;
     } catch (error) {
         console.error('API call failed:', error);
         return {
             success: false,
-            error: 'Network error: ' + error.message,
+            error: { code: 'network_error', message: 'Network error: ' + error.message, field: null },
             result: 0
         };
     }
@@ -239,7 +240,7 @@ async function setOperator(operator) {
             calculator.currentValue = result.result.toString();
             calculator.previousValue = result.result;
         } else {
-            alert(result.error || 'Calculation failed');
+            alert(errorMessage(result.error, 'Calculation failed'));
             return;
         }
     }
@@ -268,7 +269,7 @@ async function calculate() {
         calculator.waitingForOperand = true;
         updateDisplay();
     } else {
-        alert(result.error || 'Calculation failed');
+        alert(errorMessage(result.error, 'Calculation failed'));
     }
 }
 
@@ -278,6 +279,10 @@ async function performCalculation(firstValue, secondValue, operation) {
     return response;
 }
 
+function errorMessage(error, fallback) {
+    return error && error.message ? error.message : fallback;
+}
+
 function getAPIOperation(operator) {
     switch (operator) {
         case '+': return 'add';
@@ -312,38 +317,62 @@ async function calculateFunction(func) {
             calculator.waitingForOperand = true;
             updateDisplay();
         } else {
-            alert(response.error || 'Calculation failed');
+            alert(errorMessage(response.error, 'Calculation failed'));
         }
     } catch (error) {
         alert('Error: ' + error.message);
     }
 }
 
-// Memory functions
+// Memory functions (memory lives in the server-side session)
+async function callMemoryAPI(path, value) {
+    try {
+        const body = { register: memoryRegister.value };
+        if (value !== undefined) {
+            body.value = value;
+        }
+        const options = path
+            ? {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify(body)
+            }
+            : { method: 'GET' };
+        const response = await fetch(`${API_BASE}/memory${path ? '/' + path : ''}`, options);
+        const data = await response.json();
+        calculator.memory = data.memory;
+        calculator.registers = data.registers;
+        updateMemoryIndicator();
+        return data;
+    } catch (error) {
+        console.error('Memory API call failed:', error);
+    }
+}
+
 function memoryStore() {
-    calculator.memory = parseFloat(calculator.currentValue);
-    updateMemoryIndicator();
+    return callMemoryAPI('store', parseFloat(calculator.currentValue));
 }
 
-function memoryRecall() {
-    calculator.currentValue = calculator.memory.toString();
+async function memoryRecall() {
+    const data = await callMemoryAPI('recall');
+    if (!data) {
+        return;
+    }
+    calculator.currentValue = data.value.toString();
     calculator.waitingForOperand = true;
     updateDisplay();
 }
 
 function memoryAdd() {
-    calculator.memory += parseFloat(calculator.currentValue);
-    updateMemoryIndicator();
+    return callMemoryAPI('add', parseFloat(calculator.currentValue));
 }
 
 function memorySubtract() {
-    calculator.memory -= parseFloat(calculator.currentValue);
-    updateMemoryIndicator();
+    return callMemoryAPI('subtract', parseFloat(calculator.currentValue));
 }
 
 function memoryClear() {
-    calculator.memory = 0;
-    updateMemoryIndicator();
+    return callMemoryAPI('clear');
 }
 
 // History functions
@@ -376,7 +405,7 @@ async function calculatePower() {
             calculator.waitingForOperand = true;
             updateDisplay();
         } else {
-            alert(response.error || 'Power calculation failed');
+            alert(errorMessage(response.error, 'Power calculation failed'));
         }
     }
 }
@@ -0,0 +1,27 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `hello-ngrok repl` without a history file, feeding it `input`.
+fn repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hello-ngrok"))
+        .args(["repl", "--no-history-file"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn exits_successfully_when_input_ends_at_the_menu() {
+    assert!(repl("1\n1\n2\n3\n").status.success());
+}
+
+#[test]
+fn exits_with_failure_when_input_ends_mid_operation() {
+    let output = repl("1\n1\n2\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Error: input ended unexpectedly\n");
+}